@@ -1,4 +1,4 @@
-use crate::{core::UploadedLog, error::Result, log as logg, target::Target};
+use crate::{core::UploadedLog, error::Result, target::Target};
 
 use std::{
     collections::HashMap,
@@ -9,6 +9,7 @@ use std::{
 use serde::{de::DeserializeOwned, Serialize};
 use tokio::fs;
 
+#[derive(Debug)]
 pub struct Cache<K, V>
 where
     K: Serialize + DeserializeOwned + std::cmp::Eq + std::hash::Hash,
@@ -28,7 +29,7 @@ where
         let path = path.as_ref().to_owned();
         let map: HashMap<K, V> = if path.is_file() {
             let file = fs::File::open(&path).await?.into_std().await;
-            bincode::deserialize_from(file)?
+            Self::deserialize_from(&path, file)?
         } else {
             HashMap::new()
         };
@@ -45,7 +46,7 @@ where
         let path = path.as_ref().to_owned();
         let map: HashMap<K, V> = if path.is_file() {
             let file = std::fs::File::open(&path)?;
-            bincode::deserialize_from(file)?
+            Self::deserialize_from(&path, file)?
         } else {
             HashMap::new()
         };
@@ -58,9 +59,37 @@ where
         })
     }
 
+    // caches that are meant to be human-readable (and tolerate new fields)
+    // are stored as JSON, everything else as bincode
+    fn is_json(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == "json")
+    }
+
+    fn deserialize_from(path: &Path, file: std::fs::File) -> Result<HashMap<K, V>> {
+        let reader = std::io::BufReader::new(file);
+        if Self::is_json(path) {
+            Ok(serde_json::from_reader(reader)?)
+        } else {
+            Ok(bincode::deserialize_from(reader)?)
+        }
+    }
+
     fn save_blocking(&self) -> Result<()> {
         let file = std::fs::File::create(&self.path)?;
-        bincode::serialize_into(file, &self.map)?;
+        if Self::is_json(&self.path) {
+            serde_json::to_writer_pretty(file, &self.map)?;
+        } else {
+            bincode::serialize_into(file, &self.map)?;
+        }
+        Ok(())
+    }
+
+    // writes changes out right away instead of waiting to be dropped
+    pub fn save(&mut self) -> Result<()> {
+        if self.modified {
+            self.save_blocking()?;
+            self.modified = false;
+        }
         Ok(())
     }
 
@@ -74,10 +103,7 @@ where
     }
 
     pub fn remove(&mut self, key: &K) -> Option<V> {
-        self.map.remove(key).map(|v| {
-            self.modified = true;
-            v
-        })
+        self.map.remove(key).inspect(|_| self.modified = true)
     }
 
     pub fn raw(&self) -> &HashMap<K, V> {
@@ -102,7 +128,7 @@ where
 
 pub trait LogCacher {
     fn insert(&self, log: &UploadedLog);
-    fn get(&self, log: &logg::Log) -> Option<String>;
+    fn get(&self, log: &crate::log::Log) -> Option<String>;
 }
 
 #[derive(Clone)]
@@ -110,7 +136,7 @@ pub struct Nop {}
 
 impl LogCacher for Nop {
     fn insert(&self, _: &UploadedLog) {}
-    fn get(&self, _: &logg::Log) -> Option<String> {
+    fn get(&self, _: &crate::log::Log) -> Option<String> {
         None
     }
 }
@@ -138,13 +164,15 @@ impl Log {
 
 impl LogCacher for Log {
     fn insert(&self, log: &UploadedLog) {
-        self.cache
-            .lock()
-            .unwrap()
-            .insert(log.log.target(), log.link.clone());
+        if let Some(link) = &log.link {
+            self.cache
+                .lock()
+                .unwrap()
+                .insert(log.log.target(), link.clone());
+        }
     }
 
-    fn get(&self, log: &logg::Log) -> Option<String> {
+    fn get(&self, log: &crate::log::Log) -> Option<String> {
         if let Some(link) = self.cache.lock().unwrap().get(&log.target()) {
             if log.same_as(link) {
                 return Some(link.clone());
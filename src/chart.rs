@@ -0,0 +1,503 @@
+use crate::{
+    core::LogInfo,
+    error::Result,
+    message::{fmt_time2, fmt_time3},
+    sink::html_escape,
+    template::Squad,
+};
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+// what charts are saved as, discord only shows pngs in embeds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl Format {
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Svg => "svg",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Charts {
+    // a chart of each log goes along with the message about it
+    pub enabled: bool,
+    pub format: Format,
+    // where charts are kept, a directory in the system's temporary one if
+    // not set, which they're removed from once sent
+    pub dir: Option<PathBuf>,
+}
+
+impl Charts {
+    // draws `log` & saves it, returning where to
+    pub fn draw(&self, log: &LogInfo, squad: &Squad) -> Result<PathBuf> {
+        let dir = self
+            .dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("snek"));
+        fs::create_dir_all(&dir)?;
+
+        let stem = log.log.log.path().file_stem().unwrap_or_default();
+        let path = dir.join(format!(
+            "{}-{:?}.{}",
+            stem.to_string_lossy(),
+            log.encounter.target,
+            self.format.extension()
+        ));
+        let chart = Chart::new(log, squad);
+        match self.format {
+            Format::Png => fs::write(&path, chart.png()?)?,
+            Format::Svg => fs::write(&path, chart.svg())?,
+        }
+        log::info!("drew chart of {}", path.display());
+        Ok(path)
+    }
+
+    // done with `charts`, which only stay around if they're in `dir`
+    pub fn remove<P: AsRef<Path>>(&self, charts: impl IntoIterator<Item = P>) {
+        if self.dir.is_some() {
+            return;
+        }
+        for chart in charts {
+            let chart = chart.as_ref();
+            if let Err(e) = fs::remove_file(chart) {
+                log::warn!("failed to remove chart {}: {}", chart.display(), e);
+            }
+        }
+    }
+}
+
+const WIDTH: u32 = 640;
+const MARGIN: u32 = 16;
+const GRAPH_TOP: u32 = 48;
+const GRAPH_HEIGHT: u32 = 120;
+const ROW_HEIGHT: u32 = 20;
+// where dps bars start, after the names
+const BAR_LEFT: u32 = MARGIN + 168;
+const BAR_WIDTH: u32 = WIDTH - BAR_LEFT - MARGIN - 64;
+
+const BACKGROUND: u32 = 0x2f_31_36;
+const TEXT: u32 = 0xdc_dd_de;
+const GAP: u32 = 0x3a_3d_43;
+const PHASES: [u32; 2] = [0x1c_4f_75, 0x24_3f_55];
+const HEALTH: u32 = 0xd1_3e_15;
+const BAR: u32 = 0x15_83_d1;
+
+// glyphs are 5x7, in a cell of 6x8 times the size of the text
+const GLYPH_WIDTH: u32 = 6;
+const GLYPH_HEIGHT: u32 = 8;
+
+#[derive(Debug, Clone)]
+enum Shape {
+    Rect {
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+        color: u32,
+    },
+    Line {
+        points: Vec<(u32, u32)>,
+        color: u32,
+    },
+    // from the top left
+    Text {
+        x: u32,
+        y: u32,
+        size: u32,
+        text: String,
+        color: u32,
+    },
+}
+
+// the phases of a fight along with the boss' health over them, and how much
+// damage each player did
+#[derive(Debug, Clone)]
+pub struct Chart {
+    width: u32,
+    height: u32,
+    shapes: Vec<Shape>,
+}
+
+impl Chart {
+    pub fn new(log: &LogInfo, squad: &Squad) -> Self {
+        let encounter = &log.encounter;
+        let fight = &encounter.phases[0];
+        let status = if encounter.success {
+            "Success"
+        } else {
+            "Defeat"
+        };
+
+        let mut shapes = vec![Shape::Text {
+            x: MARGIN,
+            y: MARGIN,
+            size: 2,
+            text: format!(
+                "{} - {} in {}",
+                encounter.target,
+                status,
+                fmt_time3(fight.duration())
+            ),
+            color: TEXT,
+        }];
+
+        // the timeline, phases in front of the time between them
+        let graph_width = WIDTH - 2 * MARGIN;
+        let x = |time: u64| {
+            let time = time.clamp(fight.start(), fight.end()) - fight.start();
+            MARGIN + (time * graph_width as u64 / fight.duration().max(1)) as u32
+        };
+        shapes.push(Shape::Rect {
+            x: MARGIN,
+            y: GRAPH_TOP,
+            w: graph_width,
+            h: GRAPH_HEIGHT,
+            color: GAP,
+        });
+        for (idx, phase) in encounter.phases.iter().enumerate().skip(1) {
+            let (left, right) = (x(phase.start()), x(phase.end()));
+            shapes.push(Shape::Rect {
+                x: left,
+                y: GRAPH_TOP,
+                w: right - left,
+                h: GRAPH_HEIGHT,
+                color: PHASES[idx % 2],
+            });
+
+            // labels only go where they fit
+            let name = phase
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Phase {}", idx));
+            let labels = [name, fmt_time2(phase.duration())];
+            for (line, label) in labels.iter().enumerate() {
+                if text_width(label, 1) + 4 <= right - left {
+                    shapes.push(Shape::Text {
+                        x: left + 2,
+                        y: GRAPH_TOP + GRAPH_HEIGHT + 4 + line as u32 * GLYPH_HEIGHT,
+                        size: 1,
+                        text: label.clone(),
+                        color: TEXT,
+                    });
+                }
+            }
+        }
+
+        if !log.log.health.is_empty() {
+            let points = log
+                .log
+                .health
+                .iter()
+                .map(|health| {
+                    let percent = health.percent.clamp(0.0, 100.0);
+                    let drop = (100.0 - percent) / 100.0 * (GRAPH_HEIGHT - 1) as f64;
+                    (x(health.time), GRAPH_TOP + drop.round() as u32)
+                })
+                .collect();
+            shapes.push(Shape::Line {
+                points,
+                color: HEALTH,
+            });
+            // top right, the boss is never at full health by the end
+            let legend = "Boss health";
+            shapes.push(Shape::Text {
+                x: WIDTH - MARGIN - text_width(legend, 1) - 4,
+                y: GRAPH_TOP + 4,
+                size: 1,
+                text: legend.to_string(),
+                color: HEALTH,
+            });
+        }
+
+        // a bar per player, as long as the most damage allows
+        let names = squad.names(&log.log.players, log.log.recorder.as_deref());
+        let secs = (fight.duration() as f64 / 1000.0).max(1.0);
+        let dps: Vec<(&String, u64)> = log
+            .log
+            .damage
+            .iter()
+            .filter_map(|player| {
+                let dps = (player.damage as f64 / secs).round() as u64;
+                Some((names.get(&player.player)?, dps))
+            })
+            .collect();
+        let top = GRAPH_TOP + GRAPH_HEIGHT + 4 + 2 * GLYPH_HEIGHT + MARGIN;
+        let most = dps.iter().map(|(_, dps)| *dps).max().unwrap_or(0).max(1);
+        if !dps.is_empty() {
+            shapes.push(Shape::Text {
+                x: MARGIN,
+                y: top,
+                size: 1,
+                text: "DPS".to_string(),
+                color: TEXT,
+            });
+        }
+        for (idx, (name, dps)) in dps.iter().enumerate() {
+            let y = top + (idx as u32 + 1) * ROW_HEIGHT;
+            let w = ((*dps * BAR_WIDTH as u64) / most) as u32;
+            let name: String = name.chars().take(26).collect();
+            shapes.push(Shape::Text {
+                x: MARGIN,
+                y: y + 4,
+                size: 1,
+                text: name,
+                color: TEXT,
+            });
+            shapes.push(Shape::Rect {
+                x: BAR_LEFT,
+                y,
+                w: w.max(1),
+                h: ROW_HEIGHT - 4,
+                color: BAR,
+            });
+            shapes.push(Shape::Text {
+                x: BAR_LEFT + w + 6,
+                y: y + 4,
+                size: 1,
+                text: dps.to_string(),
+                color: TEXT,
+            });
+        }
+
+        let height = if dps.is_empty() {
+            top
+        } else {
+            top + (dps.len() as u32 + 1) * ROW_HEIGHT + MARGIN
+        };
+        Self {
+            width: WIDTH,
+            height,
+            shapes,
+        }
+    }
+
+    pub fn svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+            self.width, self.height
+        );
+        svg += &format!(
+            "<rect width=\"100%\" height=\"100%\" fill=\"#{:06x}\"/>\n",
+            BACKGROUND
+        );
+        for shape in &self.shapes {
+            svg += &match shape {
+                Shape::Rect { x, y, w, h, color } => format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#{:06x}\"/>\n",
+                    x, y, w, h, color
+                ),
+                Shape::Line { points, color } => {
+                    let points: Vec<String> =
+                        points.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+                    format!(
+                        "<polyline points=\"{}\" fill=\"none\" stroke=\"#{:06x}\" stroke-width=\"2\"/>\n",
+                        points.join(" "),
+                        color
+                    )
+                }
+                Shape::Text {
+                    x,
+                    y,
+                    size,
+                    text,
+                    color,
+                } => format!(
+                    "<text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"{}\" dominant-baseline=\"hanging\" fill=\"#{:06x}\">{}</text>\n",
+                    x,
+                    y,
+                    size * 10,
+                    color,
+                    html_escape(text)
+                ),
+            };
+        }
+        svg + "</svg>\n"
+    }
+
+    pub fn png(&self) -> Result<Vec<u8>> {
+        let mut canvas = Canvas::new(self.width, self.height);
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect { x, y, w, h, color } => canvas.fill(*x, *y, *w, *h, *color),
+                Shape::Line { points, color } => {
+                    for pair in points.windows(2) {
+                        canvas.line(pair[0], pair[1], *color);
+                    }
+                }
+                Shape::Text {
+                    x,
+                    y,
+                    size,
+                    text,
+                    color,
+                } => canvas.text(*x, *y, *size, text, *color),
+            }
+        }
+        canvas.png()
+    }
+}
+
+fn text_width(text: &str, size: u32) -> u32 {
+    text.chars().count() as u32 * GLYPH_WIDTH * size
+}
+
+// rgb pixels, row by row
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        let mut canvas = Self {
+            width,
+            height,
+            pixels: vec![0; (width * height * 3) as usize],
+        };
+        canvas.fill(0, 0, width, height, BACKGROUND);
+        canvas
+    }
+
+    // clipped to the canvas
+    fn fill(&mut self, x: u32, y: u32, w: u32, h: u32, color: u32) {
+        let [_, r, g, b] = color.to_be_bytes();
+        for y in y..(y + h).min(self.height) {
+            for x in x..(x + w).min(self.width) {
+                let at = ((y * self.width + x) * 3) as usize;
+                self.pixels[at..at + 3].copy_from_slice(&[r, g, b]);
+            }
+        }
+    }
+
+    // 2 pixels wide
+    fn line(&mut self, (x0, y0): (u32, u32), (x1, y1): (u32, u32), color: u32) {
+        let (x0, y0, x1, y1) = (x0 as i64, y0 as i64, x1 as i64, y1 as i64);
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).max(1);
+        for step in 0..=steps {
+            let x = x0 + (x1 - x0) * step / steps;
+            let y = y0 + (y1 - y0) * step / steps;
+            self.fill(x as u32, y as u32, 2, 2, color);
+        }
+    }
+
+    // anything the font doesn't have shows up as `?`
+    fn text(&mut self, x: u32, y: u32, size: u32, text: &str, color: u32) {
+        for (idx, c) in text.chars().enumerate() {
+            let glyph = match c {
+                ' '..='~' => FONT[c as usize - 0x20],
+                _ => FONT['?' as usize - 0x20],
+            };
+            let left = x + idx as u32 * GLYPH_WIDTH * size;
+            for (col, bits) in glyph.iter().enumerate() {
+                for row in 0..7 {
+                    if bits & (1 << row) != 0 {
+                        let (px, py) = (left + col as u32 * size, y + row * size);
+                        self.fill(px, py, size, size, color);
+                    }
+                }
+            }
+        }
+    }
+
+    fn png(&self) -> Result<Vec<u8>> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| {
+                writer.write_image_data(&self.pixels)?;
+                writer.finish()
+            })
+            .map_err(io::Error::from)?;
+        Ok(png)
+    }
+}
+
+// printable ascii, a byte per column with the top row in the lowest bit
+#[rustfmt::skip]
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], [0x00, 0x00, 0x5f, 0x00, 0x00], [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], [0x24, 0x2a, 0x7f, 0x2a, 0x12], [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x55, 0x22, 0x50], [0x00, 0x05, 0x03, 0x00, 0x00], [0x00, 0x1c, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1c, 0x00], [0x08, 0x2a, 0x1c, 0x2a, 0x08], [0x08, 0x08, 0x3e, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00], [0x08, 0x08, 0x08, 0x08, 0x08], [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02], [0x3e, 0x51, 0x49, 0x45, 0x3e], [0x00, 0x42, 0x7f, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46], [0x21, 0x41, 0x45, 0x4b, 0x31], [0x18, 0x14, 0x12, 0x7f, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39], [0x3c, 0x4a, 0x49, 0x49, 0x30], [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36], [0x06, 0x49, 0x49, 0x29, 0x1e], [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00], [0x08, 0x14, 0x22, 0x41, 0x00], [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08], [0x02, 0x01, 0x51, 0x09, 0x06], [0x32, 0x49, 0x79, 0x41, 0x3e],
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], [0x7f, 0x49, 0x49, 0x49, 0x36], [0x3e, 0x41, 0x41, 0x41, 0x22],
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], [0x7f, 0x49, 0x49, 0x49, 0x41], [0x7f, 0x09, 0x09, 0x09, 0x01],
+    [0x3e, 0x41, 0x49, 0x49, 0x7a], [0x7f, 0x08, 0x08, 0x08, 0x7f], [0x00, 0x41, 0x7f, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3f, 0x01], [0x7f, 0x08, 0x14, 0x22, 0x41], [0x7f, 0x40, 0x40, 0x40, 0x40],
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f], [0x7f, 0x04, 0x08, 0x10, 0x7f], [0x3e, 0x41, 0x41, 0x41, 0x3e],
+    [0x7f, 0x09, 0x09, 0x09, 0x06], [0x3e, 0x41, 0x51, 0x21, 0x5e], [0x7f, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31], [0x01, 0x01, 0x7f, 0x01, 0x01], [0x3f, 0x40, 0x40, 0x40, 0x3f],
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], [0x3f, 0x40, 0x38, 0x40, 0x3f], [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x07, 0x08, 0x70, 0x08, 0x07], [0x61, 0x51, 0x49, 0x45, 0x43], [0x00, 0x7f, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20], [0x00, 0x41, 0x41, 0x7f, 0x00], [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40], [0x00, 0x01, 0x02, 0x04, 0x00], [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7f, 0x48, 0x44, 0x44, 0x38], [0x38, 0x44, 0x44, 0x44, 0x20], [0x38, 0x44, 0x44, 0x48, 0x7f],
+    [0x38, 0x54, 0x54, 0x54, 0x18], [0x08, 0x7e, 0x09, 0x01, 0x02], [0x0c, 0x52, 0x52, 0x52, 0x3e],
+    [0x7f, 0x08, 0x04, 0x04, 0x78], [0x00, 0x44, 0x7d, 0x40, 0x00], [0x20, 0x40, 0x44, 0x3d, 0x00],
+    [0x7f, 0x10, 0x28, 0x44, 0x00], [0x00, 0x41, 0x7f, 0x40, 0x00], [0x7c, 0x04, 0x18, 0x04, 0x78],
+    [0x7c, 0x08, 0x04, 0x04, 0x78], [0x38, 0x44, 0x44, 0x44, 0x38], [0x7c, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7c], [0x7c, 0x08, 0x04, 0x04, 0x08], [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3f, 0x44, 0x40, 0x20], [0x3c, 0x40, 0x40, 0x20, 0x7c], [0x1c, 0x20, 0x40, 0x20, 0x1c],
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], [0x44, 0x28, 0x10, 0x28, 0x44], [0x0c, 0x50, 0x50, 0x50, 0x3c],
+    [0x44, 0x64, 0x54, 0x4c, 0x44], [0x00, 0x08, 0x36, 0x41, 0x00], [0x00, 0x00, 0x7f, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00], [0x08, 0x04, 0x08, 0x10, 0x08],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_pngs() {
+        let mut canvas = Canvas::new(3, 2);
+        canvas.fill(1, 1, 5, 5, 0x12_34_56);
+        assert_eq!(&canvas.pixels[12..], &[0x12, 0x34, 0x56, 0x12, 0x34, 0x56]);
+
+        let png = canvas.png().unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        // the header's length, kind, width & height
+        assert_eq!(&png[8..24], b"\0\0\0\x0dIHDR\0\0\0\x03\0\0\0\x02");
+        assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+        assert_eq!(text_width("Arkk", 2), 48);
+    }
+
+    #[test]
+    fn removes_temporary_charts() {
+        let path = std::env::temp_dir().join("snek-chart.png");
+        fs::write(&path, b"").unwrap();
+        let kept = Charts {
+            dir: Some(std::env::temp_dir()),
+            ..Charts::default()
+        };
+        kept.remove(Some(&path));
+        assert!(path.exists());
+
+        Charts::default().remove(Some(&path));
+        assert!(!path.exists());
+    }
+}
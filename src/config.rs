@@ -0,0 +1,162 @@
+use crate::{
+    chart::Charts,
+    core::{Window, MAX_UPLOADS},
+    error::Result,
+    retry::Retry,
+    template::Templates,
+    upload::{self, Kind, UploadOptions, Uploader},
+};
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PATH: &str = "snek.toml";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub log_dir: Option<PathBuf>,
+    // where logs get uploaded to unless a webhook says otherwise
+    pub uploader: Kind,
+    pub upload_url: String,
+    pub wingman_url: String,
+    // where Elite Insights writes its reports, next to the logs if not set
+    pub ei_output_dir: Option<PathBuf>,
+    // what the reports are served under, if anywhere
+    pub ei_base_url: Option<String>,
+    // how far back `daily` and `links` look for logs
+    pub daily_window: Window,
+    // minutes between pulls before they're considered separate sessions
+    pub session_gap: u64,
+    // seconds before a request or just connecting is given up on
+    pub timeout: u64,
+    pub connect_timeout: u64,
+    // how many logs are uploaded at the same time at most
+    pub max_uploads: usize,
+    // send messages without a link when uploading fails instead of queueing
+    pub failover: bool,
+    // attach logs that didn't end up with a link to the message
+    pub attach_logs: bool,
+    // tables have to come after plain values for the config to serialize
+    pub upload: UploadOptions,
+    pub retry: Retry,
+    pub templates: Templates,
+    pub charts: Charts,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            log_dir: None,
+            uploader: Kind::DpsReport,
+            upload_url: "https://dps.report/".to_string(),
+            wingman_url: "https://gw2wingman.nevermindcreations.de/".to_string(),
+            ei_output_dir: None,
+            ei_base_url: None,
+            daily_window: Window::Reset,
+            session_gap: 60,
+            timeout: 120,
+            connect_timeout: 10,
+            max_uploads: MAX_UPLOADS,
+            failover: false,
+            attach_logs: false,
+            upload: UploadOptions::default(),
+            retry: Retry::default(),
+            templates: Templates::default(),
+            charts: Charts::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    // an http client with the configured timeouts
+    pub fn client(&self) -> Result<reqwest::Client> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(self.timeout))
+            .connect_timeout(Duration::from_secs(self.connect_timeout))
+            .build()?;
+        Ok(client)
+    }
+
+    pub fn uploader(&self, kind: Kind) -> Result<Arc<dyn Uploader>> {
+        let uploader: Arc<dyn Uploader> = match kind {
+            Kind::DpsReport => Arc::new(
+                upload::DpsReport::new(&self.upload_url, self.upload.clone())
+                    .with_client(self.client()?),
+            ),
+            Kind::Wingman => {
+                Arc::new(upload::Wingman::new(&self.wingman_url).with_client(self.client()?))
+            }
+            Kind::Local => Arc::new(upload::Local::new(
+                self.ei_output_dir.clone(),
+                self.ei_base_url.clone(),
+            )),
+            Kind::Offline => Arc::new(upload::Offline {}),
+        };
+        Ok(uploader)
+    }
+
+    // a missing config at the default location just means defaults
+    pub fn load_or_default(path: impl AsRef<Path>) -> Result<Self> {
+        if path.as_ref().is_file() {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+// sets the dps.report user token in the config at `path`, leaving everything
+// else in it as it was
+pub fn save_token(path: impl AsRef<Path>, token: &str) -> Result<()> {
+    let path = path.as_ref();
+    let contents = if path.is_file() {
+        std::fs::read_to_string(path)?
+    } else {
+        String::new()
+    };
+    let mut config: toml_edit::DocumentMut = contents.parse()?;
+    config.entry("upload").or_insert(toml_edit::table())["user_token"] = toml_edit::value(token);
+    std::fs::write(path, config.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn saves_only_the_token() {
+        let path = std::env::temp_dir().join("snek-token.toml");
+        std::fs::write(
+            &path,
+            "# where logs go\nuploader = \"wingman\"\n\n[upload]\nanonymous = true # shh\n",
+        )
+        .unwrap();
+
+        super::save_token(&path, "abc").unwrap();
+        assert_eq!(
+            "# where logs go\nuploader = \"wingman\"\n\n[upload]\nanonymous = true # shh\nuser_token = \"abc\"\n",
+            std::fs::read_to_string(&path).unwrap()
+        );
+        super::save_token(&path, "def").unwrap();
+        let config = super::Config::load(&path).unwrap();
+        assert_eq!(Some("def"), config.upload.user_token.as_deref());
+
+        std::fs::remove_file(&path).unwrap();
+        super::save_token(&path, "abc").unwrap();
+        assert_eq!(
+            "[upload]\nuser_token = \"abc\"\n",
+            std::fs::read_to_string(&path).unwrap()
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
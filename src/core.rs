@@ -1,60 +1,504 @@
 use crate::{
     cache::LogCacher,
+    chart::Charts,
     error::{Error, Result},
     filter::{self, Filter},
+    history::{Comparison, DamageRecord, HealthRecord, History, Record},
+    inspect::{EventFilter, Inspection},
+    live::Live,
     log::Log,
-    message,
-    parse::{self, Encounter},
-    sender::{self, Sender, Webhook},
+    message::{self, Format, Mentions},
+    parse::{self, Data, Encounter, Time},
+    queue::{Job, Queue},
+    report::{self, Report},
+    retry::Retry,
+    sender::{self, Sender},
+    session::{self, Pull},
+    spec::Spec,
     target::Target,
-    upload,
+    template::Templates,
+    upload::{self, Uploader},
     watcher::{Event, Watcher},
 };
 
 use std::{
+    collections::BTreeMap,
     fs,
     io::Write,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
-use tokio::sync::Mutex;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, Semaphore};
 
-pub async fn links<P, W, C>(log_dir: P, url: &str, writer: W, cache: C) -> Result<()>
+// how many logs are uploaded at once unless configured otherwise
+pub const MAX_UPLOADS: usize = 2;
+
+// how often watch checks whether anything queued is due to be tried again
+const QUEUE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub uploader: Arc<dyn Uploader>,
+    pub retry: Retry,
+    // caps how many uploads run at the same time, shared between clones
+    pub uploads: Arc<Semaphore>,
+    pub dry_run: bool,
+    pub window: Window,
+    // where uploaded logs are remembered, if anywhere
+    pub history: Option<History>,
+    // where uploads and messages that failed wait to be tried again
+    pub queue: Option<Queue>,
+    // keeps one message per session up to date in watch instead of sending
+    // one per log
+    pub live: Option<Live>,
+    // who gets pinged about records & wipes
+    pub mentions: Mentions,
+    // how messages about logs look
+    pub templates: Templates,
+    // send messages without a link if uploading fails instead of giving up
+    pub failover: bool,
+    // attach logs that have no link to the messages about them
+    pub attach_logs: bool,
+    // draw charts of logs to go along with the messages about them
+    pub charts: Charts,
+}
+
+impl Options {
+    // uploads to dps.report or a stand-in for it at `url`
+    pub fn new(url: &str) -> Self {
+        Self {
+            uploader: Arc::new(upload::DpsReport::new(url, Default::default())),
+            retry: Retry::default(),
+            uploads: Arc::new(Semaphore::new(MAX_UPLOADS)),
+            dry_run: false,
+            window: Window::Reset,
+            history: None,
+            queue: None,
+            live: None,
+            mentions: Mentions::default(),
+            templates: Templates::default(),
+            failover: false,
+            attach_logs: false,
+            charts: Charts::default(),
+        }
+    }
+
+    pub const fn retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn max_uploads(mut self, max_uploads: usize) -> Self {
+        self.uploads = Arc::new(Semaphore::new(max_uploads.max(1)));
+        self
+    }
+
+    pub const fn failover(mut self, failover: bool) -> Self {
+        self.failover = failover;
+        self
+    }
+
+    pub const fn attach_logs(mut self, attach_logs: bool) -> Self {
+        self.attach_logs = attach_logs;
+        self
+    }
+
+    pub const fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub const fn window(mut self, window: Window) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn history(mut self, history: History) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    pub fn queue(mut self, queue: Queue) -> Self {
+        self.queue = Some(queue);
+        self
+    }
+
+    pub fn live(mut self, live: Live) -> Self {
+        self.live = Some(live);
+        self
+    }
+
+    pub fn mentions(mut self, mentions: Mentions) -> Self {
+        self.mentions = mentions;
+        self
+    }
+
+    pub fn templates(mut self, templates: Templates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    pub fn charts(mut self, charts: Charts) -> Self {
+        self.charts = charts;
+        self
+    }
+
+    pub fn uploader(mut self, uploader: Arc<dyn Uploader>) -> Self {
+        self.uploader = uploader;
+        self
+    }
+}
+
+// how far back to look when picking the most recent log of a target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Window {
+    // since the last daily reset at 00:00 UTC
+    Reset,
+    Hours(u64),
+    All,
+}
+
+impl Window {
+    // unix timestamp of the start of the window, relative to `now`
+    pub fn start(self, now: i64) -> Option<i64> {
+        use std::convert::TryFrom;
+
+        const DAY: i64 = 24 * 60 * 60;
+        match self {
+            Self::Reset => Some(now - now.rem_euclid(DAY)),
+            // windows reaching further back than time itself cover everything
+            Self::Hours(hours) => i64::try_from(hours)
+                .ok()?
+                .checked_mul(60 * 60)
+                .map(|secs| now.saturating_sub(secs)),
+            Self::All => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Window {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "reset" => Ok(Self::Reset),
+            "all" => Ok(Self::All),
+            _ => s
+                .strip_suffix('h')
+                .and_then(|hours| hours.parse().ok())
+                .map(Self::Hours)
+                .ok_or_else(|| format!("invalid window `{}`, expected reset, all or i.e. 12h", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Window {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Reset => write!(f, "reset"),
+            Self::Hours(hours) => write!(f, "{}h", hours),
+            Self::All => write!(f, "all"),
+        }
+    }
+}
+
+impl std::convert::TryFrom<String> for Window {
+    type Error = String;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Window> for String {
+    fn from(window: Window) -> Self {
+        window.to_string()
+    }
+}
+
+pub async fn links<P, W, C>(
+    log_dir: P,
+    options: &Options,
+    writer: W,
+    cache: C,
+    format: Format,
+) -> Result<()>
 where
     P: AsRef<Path>,
     W: Write + Send + Sync,
     C: LogCacher + Sync,
 {
-    let msg_gen = message::TextGenerator {};
     let sender = sender::Write::new(writer);
     let filter = filter::Length {};
-    let mut snek = Snek::new(&log_dir, url, sender, cache, msg_gen, filter);
-    snek.upload_dailies_and_send().await
+    match format {
+        Format::Text => {
+            let msg_gen = message::TextGenerator {
+                templates: options.templates.clone(),
+            };
+            let mut snek = Snek::new(&log_dir, options, sender, cache, msg_gen, filter);
+            snek.upload_dailies_and_send().await
+        }
+        Format::Json => {
+            let msg_gen = message::JsonGenerator {};
+            let mut snek = Snek::new(&log_dir, options, sender, cache, msg_gen, filter);
+            snek.upload_dailies_and_send().await
+        }
+    }
 }
 
-pub async fn daily<P, C>(log_dir: P, url: &str, sender: Webhook, cache: C) -> Result<()>
+pub async fn daily<P, S, C>(log_dir: P, options: &Options, sender: S, cache: C) -> Result<()>
 where
     P: AsRef<Path>,
+    S: Sender,
     C: LogCacher + Sync,
 {
-    let msg_gen = message::WebhookGenerator {};
+    let msg_gen = message::WebhookGenerator {
+        mentions: options.mentions.clone(),
+        templates: options.templates.clone(),
+    };
     let filter = filter::Length {};
-    let mut snek = Snek::new(&log_dir, url, sender, cache, msg_gen, filter);
+    let mut snek = Snek::new(&log_dir, options, sender, cache, msg_gen, filter);
     snek.upload_dailies_and_send().await
 }
 
-pub async fn watch<P, C>(log_dir: P, url: &str, sender: Webhook, cache: C) -> Result<()>
+// `gap` is the number of minutes between pulls that splits two sessions
+pub async fn session<P, S, C>(
+    log_dir: P,
+    options: &Options,
+    sender: S,
+    cache: C,
+    gap: u64,
+) -> Result<()>
+where
+    P: AsRef<Path>,
+    S: Sender,
+    C: LogCacher + Sync,
+{
+    let msg_gen = message::WebhookGenerator {
+        mentions: options.mentions.clone(),
+        templates: options.templates.clone(),
+    };
+    let filter = filter::Length {};
+    let mut snek = Snek::new(&log_dir, options, sender, cache, msg_gen, filter);
+    snek.upload_session_and_send(gap).await
+}
+
+pub async fn watch<P, S, C>(log_dir: P, options: &Options, sender: S, cache: C) -> Result<()>
 where
     P: AsRef<Path>,
+    S: Sender + Sync + Send + 'static,
     C: LogCacher + Clone + Sync + Send + 'static,
 {
-    let msg_gen = message::WebhookGenerator {};
+    let msg_gen = message::WebhookGenerator {
+        mentions: options.mentions.clone(),
+        templates: options.templates.clone(),
+    };
     let filter = filter::Length {};
-    let snek = Snek::new(&log_dir, url, sender, cache, msg_gen, filter);
+    let snek = Snek::new(&log_dir, options, sender, cache, msg_gen, filter);
     snek.watch_dir().await
 }
 
+// which logs a backfill should upload, nothing set means everything
+#[derive(Debug, Clone)]
+pub struct Backfill {
+    pub since: Option<NaiveDate>,
+    pub targets: Vec<Target>,
+    // maximum number of logs being uploaded at once
+    pub jobs: usize,
+}
+
+pub async fn backfill<P, W, C>(
+    log_dir: P,
+    options: &Options,
+    backfill: &Backfill,
+    writer: W,
+    cache: C,
+    format: Format,
+) -> Result<()>
+where
+    P: AsRef<Path>,
+    W: Write + Send + Sync,
+    C: LogCacher + Sync,
+{
+    let sender = sender::Write::new(writer);
+    let filter = filter::Length {};
+    match format {
+        Format::Text => {
+            let msg_gen = message::TextGenerator {
+                templates: options.templates.clone(),
+            };
+            let mut snek = Snek::new(&log_dir, options, sender, cache, msg_gen, filter);
+            snek.backfill(backfill).await
+        }
+        Format::Json => {
+            let msg_gen = message::JsonGenerator {};
+            let mut snek = Snek::new(&log_dir, options, sender, cache, msg_gen, filter);
+            snek.backfill(backfill).await
+        }
+    }
+}
+
+// summarises everything recorded in the history between `since` and `until`
+pub async fn report<P, S, M>(
+    log_dir: P,
+    options: &Options,
+    since: NaiveDate,
+    until: NaiveDate,
+    mut sender: S,
+    msg_gen: M,
+) -> Result<()>
+where
+    P: AsRef<Path>,
+    S: Sender,
+    M: message::ReportGenerator,
+    <M as message::Generator>::Message: Sync + Send,
+{
+    // wipes never get uploaded, so only the logs themselves know about them
+    let mut pulls = find_pulls_since(
+        log_dir.as_ref(),
+        Some(report::earliest(since, until).div_euclid(1000)),
+    )?;
+    if let Some(history) = &options.history {
+        for pull in &mut pulls {
+            pull.record.link = history.link(&pull.log);
+        }
+    }
+    let report = Report::new(&pulls, since, until);
+    let msg = msg_gen.generate_report(&report);
+    sender.send(&msg).await
+}
+
+// lists the fastest recorded kill of each target, or of each target and squad
+pub fn records<W>(options: &Options, mut writer: W, format: Format, by_squad: bool) -> Result<()>
+where
+    W: Write,
+{
+    let bests = options
+        .history
+        .as_ref()
+        .map(|history| history.bests(by_squad))
+        .unwrap_or_default();
+
+    match format {
+        Format::Text if bests.is_empty() => writeln!(writer, "no kills recorded")?,
+        Format::Text => {
+            for best in &bests {
+                writeln!(writer, "{}", best)?;
+            }
+        }
+        Format::Json => writeln!(writer, "{}", serde_json::to_string_pretty(&bests)?)?,
+    }
+
+    Ok(())
+}
+
+// makes the log at `path` the reference its target's kills are compared against
+pub fn pin<W>(options: &Options, path: &Path, mut writer: W) -> Result<()>
+where
+    W: Write,
+{
+    let log = Log::from_file_checked(path).ok_or_else(|| Error::UnsupportedLog(path.into()))?;
+    let (encounters, data) =
+        parse::parse(&log).ok_or_else(|| Error::UnsupportedLog(path.into()))?;
+    let record = Record::new(&log, None, &encounters, &data);
+    if !encounters.iter().any(|encounter| encounter.success) {
+        log::warn!(
+            "{} isn't a kill, so there's nothing to compare against",
+            log.file_name()
+        );
+    }
+
+    if let Some(history) = &options.history {
+        writeln!(writer, "pinned {} {}", record.target, record.file)?;
+        history.pin(&log, record);
+    }
+    Ok(())
+}
+
+pub fn unpin<W>(options: &Options, target: Target, mut writer: W) -> Result<()>
+where
+    W: Write,
+{
+    let unpinned = options
+        .history
+        .as_ref()
+        .is_some_and(|history| history.unpin(target));
+    if unpinned {
+        writeln!(writer, "unpinned {}", target)?;
+    } else {
+        writeln!(writer, "nothing pinned for {}", target)?;
+    }
+    Ok(())
+}
+
+// parses logs without uploading them, writing a summary of each encounter
+pub async fn parse<W>(paths: &[PathBuf], writer: W, format: Format) -> Result<()>
+where
+    W: Write + Send + Sync,
+{
+    let mut summaries = Vec::new();
+    for path in paths {
+        let log =
+            Log::from_file_checked(path).ok_or_else(|| Error::UnsupportedLog(path.clone()))?;
+        let (encounters, _) =
+            parse::parse(&log).ok_or_else(|| Error::UnsupportedLog(path.clone()))?;
+        summaries.extend(
+            encounters
+                .iter()
+                .map(|encounter| message::Summary::new(&log, None, encounter)),
+        );
+    }
+
+    let mut sender = sender::Write::new(writer);
+    match format {
+        Format::Text => {
+            for summary in &summaries {
+                sender.send(summary).await?;
+            }
+            Ok(())
+        }
+        Format::Json => sender.send(message::Json::new(summaries)).await,
+    }
+}
+
+// parses a single log and writes everything snek knows about it
+pub fn inspect<W>(
+    path: &Path,
+    mut writer: W,
+    format: Format,
+    filter: Option<&EventFilter>,
+    trace: bool,
+) -> Result<()>
+where
+    W: Write,
+{
+    let unsupported = || Error::UnsupportedLog(path.to_owned());
+    let log = Log::from_file_checked(path).ok_or_else(unsupported)?;
+    let (encounters, data, parse_trace) = parse::parse_with_trace(&log).ok_or_else(unsupported)?;
+    let parse_trace = if trace { Some(parse_trace) } else { None };
+    let inspection = Inspection::new(&log, &encounters, &data, parse_trace, filter);
+
+    match format {
+        Format::Text => write!(writer, "{}", inspection)?,
+        Format::Json => writeln!(writer, "{}", serde_json::to_string_pretty(&inspection)?)?,
+    }
+
+    Ok(())
+}
+
+// a log that came in while in live mode
+struct LiveUpdate<'a> {
+    pull: Pull,
+    // its encounters & what goes along with them, for senders that can't
+    // edit the session's message
+    new: &'a [LogInfo<'a>],
+    files: &'a [PathBuf],
+}
+
 struct Snek<'a, C, S, M, F>
 where
     C: LogCacher,
@@ -63,7 +507,7 @@ where
     M: message::Generator,
 {
     log_dir: &'a Path,
-    url: &'a str,
+    options: &'a Options,
     sender: S,
     cache: C,
     msg_gen: M,
@@ -75,12 +519,12 @@ where
     C: LogCacher + Sync,
     S: Sender,
     F: Filter,
-    M: message::Generator,
-    <M as message::Generator>::Message: Sync,
+    M: message::Generator + Sync,
+    <M as message::Generator>::Message: Send + Sync,
 {
     fn new(
         log_dir: &'a impl AsRef<Path>,
-        url: &'a str,
+        options: &'a Options,
         sender: S,
         cache: C,
         msg_gen: M,
@@ -89,7 +533,7 @@ where
         let log_dir = log_dir.as_ref();
         Self {
             log_dir,
-            url,
+            options,
             sender,
             cache,
             msg_gen,
@@ -101,23 +545,125 @@ where
         use Target::*;
 
         let targets = vec![Skor, Arts, Arkk, Mama, Siax, Enso];
-        let uploaded_logs = self.upload_recent_logs(&targets).await;
+        let uploaded_logs: Vec<_> = self
+            .upload_recent_logs(&targets)
+            .await
+            .into_iter()
+            .map(|uploaded_log| {
+                let (encounters, data) = parse::parse(&uploaded_log.log).unwrap();
+                let record = Record::new(
+                    &uploaded_log.log,
+                    uploaded_log.link.as_deref(),
+                    &encounters,
+                    &data,
+                );
+                let comparisons = compare(self.options, &record);
+                let uploaded_log = uploaded_log.with_record(&record);
+                remember(self.options, &uploaded_log.log, record);
+                (uploaded_log, encounters, comparisons)
+            })
+            .collect();
         let mut log_infos: Vec<LogInfo> = uploaded_logs
             .iter()
-            .map(|uploaded_log| {
-                let (encounters, _) = parse::parse(&uploaded_log.log).unwrap();
+            .flat_map(|(uploaded_log, encounters, comparisons)| {
                 encounters
-                    .into_iter()
-                    .map(|e| LogInfo::new(uploaded_log, e))
-                    .collect::<Vec<LogInfo>>()
+                    .iter()
+                    .cloned()
+                    .zip(comparisons.iter().cloned())
+                    .map(move |(e, c)| LogInfo::new(uploaded_log, e).with_comparison(c))
+            })
+            .map(|log_info| log_info.with_chart(self.options))
+            .collect();
+        log_infos.sort_by_key(|info| info.encounter.target);
+        let mut files = attachments(self.options, uploaded_logs.iter().map(|(log, _, _)| log));
+        files.extend(log_infos.iter().filter_map(|info| info.chart.clone()));
+        let res = self
+            .sender
+            .send_logs(&self.msg_gen, &log_infos, &files)
+            .await;
+        self.options
+            .charts
+            .remove(log_infos.iter().filter_map(|info| info.chart.as_ref()));
+        res
+    }
+
+    async fn backfill(&mut self, backfill: &Backfill) -> Result<()> {
+        use futures::{stream, StreamExt as _};
+
+        let logs = find_logs(self.log_dir, backfill)?;
+        log::info!("found {} logs to backfill", logs.len());
+
+        let results: Vec<_> = stream::iter(logs)
+            .map(|log| self.backfill_log(log))
+            .buffer_unordered(backfill.jobs.max(1))
+            .collect()
+            .await;
+
+        let mut uploaded_logs = Vec::new();
+        let (mut skipped, mut failed) = (0, 0);
+        for res in results {
+            match res {
+                Ok(Some(uploaded)) => uploaded_logs.push(uploaded),
+                Ok(None) => skipped += 1,
+                Err(e) => {
+                    log::warn!("failed to upload log: {}", e);
+                    failed += 1;
+                }
+            }
+        }
+        log::info!(
+            "backfill finished: {} uploaded, {} skipped, {} failed",
+            uploaded_logs.len(),
+            skipped,
+            failed
+        );
+
+        uploaded_logs.sort_by_key(|(uploaded_log, _, _)| uploaded_log.log.id());
+        let log_infos: Vec<LogInfo> = uploaded_logs
+            .iter()
+            .flat_map(|(uploaded_log, encounters, comparisons)| {
+                encounters
+                    .iter()
+                    .cloned()
+                    .zip(comparisons.iter().cloned())
+                    .map(move |(e, c)| LogInfo::new(uploaded_log, e).with_comparison(c))
             })
-            .flatten()
             .collect();
-        log_infos.sort_by(|a, b| a.encounter.target.cmp(&b.encounter.target));
         let msg = self.msg_gen.generate(&log_infos);
         self.sender.send(&msg).await
     }
 
+    // `None` if the log didn't need uploading
+    async fn backfill_log(
+        &self,
+        log: Log,
+    ) -> Result<Option<(UploadedLog, Vec<Encounter>, Vec<Option<Comparison>>)>> {
+        let history = self.options.history.as_ref();
+        if history.is_some_and(|history| history.contains(&log)) {
+            log::trace!("`{}` already uploaded", log);
+            return Ok(None);
+        }
+
+        let (encounters, data) =
+            parse::parse(&log).ok_or_else(|| Error::UnsupportedLog(log.path().to_owned()))?;
+        if !encounters.first().is_some_and(|e| self.filter.filter(e)) {
+            log::trace!("`{}` filtered out", log);
+            return Ok(None);
+        }
+
+        let uploaded_log = self.upload_log(log).await?;
+        let record = Record::new(
+            &uploaded_log.log,
+            uploaded_log.link.as_deref(),
+            &encounters,
+            &data,
+        );
+        let comparisons = compare(self.options, &record);
+        let uploaded_log = uploaded_log.with_record(&record);
+        remember(self.options, &uploaded_log.log, record);
+        Ok(Some((uploaded_log, encounters, comparisons)))
+    }
+
     async fn upload_recent_logs(&self, targets: &[Target]) -> Vec<UploadedLog> {
         use futures::{future, stream, StreamExt as _, TryStreamExt as _};
 
@@ -131,51 +677,132 @@ where
     }
 
     async fn upload_recent_log(&self, target: Target) -> Result<UploadedLog> {
-        let log = find_recent_log(self.log_dir, target).await?;
+        let log = find_recent_log(self.log_dir, target, self.options.window).await?;
         self.upload_log(log).await
     }
 
     async fn upload_log(&self, log: Log) -> Result<UploadedLog> {
         if let Some(link) = self.cache.get(&log) {
             log::info!("`{}` found in cache", log);
-            return Ok(UploadedLog::new(log, link));
+            return Ok(UploadedLog::new(log, Some(link)));
         }
 
-        log::info!("uploading log: {}", log);
-        let res = upload::push(self.url, log.path()).await?;
-        let uploaded_log = UploadedLog::new(log, res.permalink);
-        self.cache.insert(&uploaded_log);
+        let uploaded_log = upload(self.options, log).await?;
+        if !self.options.dry_run {
+            self.cache.insert(&uploaded_log);
+        }
         Ok(uploaded_log)
     }
 }
 
+impl<'a, C, S, M, F> Snek<'a, C, S, M, F>
+where
+    C: LogCacher + Sync,
+    S: Sender,
+    F: Filter,
+    M: message::SessionGenerator + Sync,
+    <M as message::Generator>::Message: Send + Sync,
+{
+    async fn upload_session_and_send(&mut self, gap: u64) -> Result<()> {
+        use futures::{future, stream, StreamExt as _, TryStreamExt as _};
+
+        let pulls = find_pulls(self.log_dir, self.options.window)?;
+        let session = session::group(pulls, gap as i64 * 60 * 1000)
+            .pop()
+            .ok_or(Error::NoRecentLog)?;
+        log::info!("found session with {} pulls", session.pulls.len());
+
+        let kills = session.kills();
+        let jobs = kills.len().max(1);
+        let this = &*self;
+        let uploaded_logs: Vec<(UploadedLog, &Pull, Vec<Option<Comparison>>)> = stream::iter(kills)
+            .map(|pull| async move {
+                let uploaded_log = this.upload_log(pull.log.clone()).await?;
+                let mut record = pull.record.clone();
+                record.link = uploaded_log.link.clone();
+                let comparisons = compare(this.options, &record);
+                let uploaded_log = uploaded_log.with_record(&record);
+                remember(this.options, &uploaded_log.log, record);
+                Ok::<_, Error>((uploaded_log, pull, comparisons))
+            })
+            .buffer_unordered(jobs)
+            .map_err(|e| log::warn!("failed to upload log: {}", e))
+            .filter_map(|res| future::ready(res.ok()))
+            .collect()
+            .await;
+
+        let mut log_infos: Vec<LogInfo> = uploaded_logs
+            .iter()
+            .flat_map(|(uploaded_log, pull, comparisons)| {
+                pull.encounters
+                    .iter()
+                    .cloned()
+                    .zip(comparisons.iter().cloned())
+                    .map(move |(e, c)| LogInfo::new(uploaded_log, e).with_comparison(c))
+            })
+            .collect();
+        log_infos.sort_by_key(|info| info.encounter.target);
+        let msg = self.msg_gen.generate_session(&session, &log_infos);
+        let files = attachments(
+            self.options,
+            uploaded_logs
+                .iter()
+                .map(|(uploaded_log, _, _)| uploaded_log),
+        );
+        self.sender.send_with_files(&msg, &files).await
+    }
+}
+
 impl<'a, C, S, M, F> Snek<'a, C, S, M, F>
 where
     C: LogCacher + Clone + Sync + Send + 'static,
     S: Sender + Sync + Send + 'static,
     F: Filter + Sync + Send + 'static,
-    M: message::Generator + Sync + Send + 'static,
+    M: message::SessionGenerator + Sync + Send + 'static,
     <M as message::Generator>::Message: Sync + Send,
 {
     async fn watch_dir(self) -> Result<()> {
         let watcher = Watcher::watch(self.log_dir);
+        let log_dir = self.log_dir.to_owned();
         let sender = Arc::new(Mutex::new(self.sender));
         let msg_gen = Arc::new(self.msg_gen);
         let filter = Arc::new(self.filter);
 
+        if self.options.queue.is_some() && !self.options.dry_run {
+            // catches up on whatever was left over from last time, then keeps
+            // checking back in case the network went away
+            let sender = Arc::clone(&sender);
+            let msg_gen = Arc::clone(&msg_gen);
+            let options = self.options.clone();
+            let cache = self.cache.clone();
+            let log_dir = log_dir.clone();
+            tokio::spawn(async move {
+                loop {
+                    Self::drain_queue(&sender, &options, &cache, &log_dir, &msg_gen).await;
+                    tokio::time::delay_for(QUEUE_INTERVAL).await;
+                }
+            });
+        }
+
         log::info!("started watching log folder");
         while let Event::File(path) = watcher.recv() {
             let sender = Arc::clone(&sender);
             let msg_gen = Arc::clone(&msg_gen);
             let filter = Arc::clone(&filter);
-            let url = self.url.to_owned();
+            let options = self.options.clone();
             let cache = self.cache.clone();
+            let log_dir = log_dir.clone();
 
             if let Some(log) = Log::from_file_checked(&path) {
                 tokio::spawn(async move {
-                    Self::handle_incoming_log(sender, &url, &cache, log, msg_gen, filter)
-                        .await
-                        .unwrap()
+                    let name = log.to_string();
+                    let res = Self::handle_incoming_log(
+                        sender, &options, &cache, &log_dir, log, msg_gen, filter,
+                    )
+                    .await;
+                    if let Err(e) = res {
+                        log::error!("failed to handle incoming log {}: {}", name, e);
+                    }
                 });
             }
         }
@@ -186,13 +813,14 @@ where
 
     async fn handle_incoming_log(
         sender: Arc<Mutex<S>>,
-        url: &str,
+        options: &Options,
         cache: &C,
+        log_dir: &Path,
         log: Log,
         msg_gen: Arc<M>,
         filter: Arc<F>,
     ) -> Result<()> {
-        let (mut encounters, _) = match parse::parse(&log) {
+        let (encounters, data) = match parse::parse(&log) {
             Some(e) => e,
             None => {
                 log::trace!("incoming log is from an unsupported encounter");
@@ -200,97 +828,600 @@ where
             }
         };
 
-        if !filter.filter(&encounters.first().unwrap()) {
+        if !filter.filter(encounters.first().unwrap()) {
             log::trace!("incoming log filtered out");
             return Ok(());
         }
 
-        log::info!("uploading log: {}", &log);
-        let response = upload::push(url, log.path()).await?;
-        let uploaded_log = UploadedLog::new(log, response.permalink);
+        let path = log.path().to_owned();
+        let uploaded_log = upload(options, log)
+            .await
+            .inspect_err(|e| enqueue(options, Job::Upload { path: path.clone() }, e))?;
 
-        cache.insert(&uploaded_log);
+        if !options.dry_run {
+            cache.insert(&uploaded_log);
+        }
 
-        if encounters.len() == 1 {
-            let log_info = LogInfo::new(&uploaded_log, encounters.remove(0));
-            let msg = msg_gen.generate(&[log_info]);
-            sender.lock().await.send(msg).await?;
-        } else {
-            use futures::{future, stream, StreamExt as _, TryStreamExt as _};
-            stream::iter(encounters.iter().cloned())
-                .map(|encounter| {
-                    let msg_gen = msg_gen.clone();
-                    let sender = sender.clone();
-                    let uploaded_log = uploaded_log.clone();
-
-                    async move {
-                        let log_info = LogInfo::new(&uploaded_log, encounter);
-                        let msg = msg_gen.generate(&[log_info]);
-                        sender.lock().await.send(msg).await?;
-                        Ok::<(), Error>(())
-                    }
+        Self::announce(
+            &sender,
+            options,
+            log_dir,
+            &uploaded_log,
+            encounters,
+            &data,
+            &msg_gen,
+        )
+        .await
+        .inspect_err(|e| {
+            let link = uploaded_log.link.clone();
+            enqueue(options, Job::Message { path, link }, e)
+        })
+    }
+
+    // records `uploaded_log` & sends a message for each of its encounters, or
+    // updates the live message of the session it's part of
+    async fn announce(
+        sender: &Arc<Mutex<S>>,
+        options: &Options,
+        log_dir: &Path,
+        uploaded_log: &UploadedLog,
+        mut encounters: Vec<Encounter>,
+        data: &Data,
+        msg_gen: &Arc<M>,
+    ) -> Result<()> {
+        let record = Record::new(
+            &uploaded_log.log,
+            uploaded_log.link.as_deref(),
+            &encounters,
+            data,
+        );
+        let mut comparisons = compare(options, &record);
+        let uploaded_log = &uploaded_log.clone().with_record(&record);
+        let files = attachments(options, Some(uploaded_log));
+        if let Some(live) = &options.live {
+            let start = log_start(&uploaded_log.log, data).ok_or(Error::NoRecentLog)?;
+            let pull = Pull::new(
+                uploaded_log.log.clone(),
+                encounters.clone(),
+                record.clone(),
+                start * 1000,
+            );
+            remember(options, &uploaded_log.log, record);
+            let mut files = files;
+            let new: Vec<LogInfo> = encounters
+                .into_iter()
+                .zip(comparisons)
+                .map(|(encounter, comparison)| {
+                    LogInfo::new(uploaded_log, encounter)
+                        .with_comparison(comparison)
+                        .with_chart(options)
                 })
-                .buffer_unordered(encounters.len())
-                .map_err(|e| log::warn!("failed to send webhook msg: {}", e))
-                .filter_map(|res| future::ready(res.ok()))
-                .collect::<Vec<_>>()
+                .inspect(|log_info| files.extend(log_info.chart.clone()))
+                .collect();
+            let live_update = LiveUpdate {
+                pull,
+                new: &new,
+                files: &files,
+            };
+            let res = Self::update_live(sender, options, log_dir, live_update, msg_gen, live).await;
+            options
+                .charts
+                .remove(new.iter().filter_map(|info| info.chart.as_ref()));
+            return res;
+        }
+        remember(options, &uploaded_log.log, record);
+
+        if encounters.len() == 1 {
+            let log_info = LogInfo::new(uploaded_log, encounters.remove(0))
+                .with_comparison(comparisons.remove(0))
+                .with_chart(options);
+            let mut files = files;
+            files.extend(log_info.chart.clone());
+            let mut sender = sender.lock().await;
+            let res = sender
+                .send_logs(&**msg_gen, std::slice::from_ref(&log_info), &files)
                 .await;
+            options.charts.remove(log_info.chart.iter());
+            res?;
+        } else {
+            use futures::{stream, StreamExt as _};
+            let results: Vec<Result<()>> =
+                stream::iter(encounters.iter().cloned().zip(comparisons).enumerate())
+                    .map(|(idx, (encounter, comparison))| {
+                        let msg_gen = msg_gen.clone();
+                        let sender = sender.clone();
+                        let uploaded_log = uploaded_log.clone();
+                        // the log only needs to go along once
+                        let mut files = if idx == 0 { files.clone() } else { Vec::new() };
+
+                        async move {
+                            let log_info = LogInfo::new(&uploaded_log, encounter)
+                                .with_comparison(comparison)
+                                .with_chart(options);
+                            files.extend(log_info.chart.clone());
+                            let mut sender = sender.lock().await;
+                            let res = sender
+                                .send_logs(&*msg_gen, std::slice::from_ref(&log_info), &files)
+                                .await;
+                            options.charts.remove(log_info.chart.iter());
+                            res
+                        }
+                    })
+                    .buffer_unordered(encounters.len())
+                    .collect()
+                    .await;
+
+            // the ones that did go through get sent again if this is retried,
+            // which beats losing the others
+            for e in results.iter().filter_map(|res| res.as_ref().err()) {
+                log::warn!("failed to send webhook msg: {}", e);
+            }
+            results.into_iter().collect::<Result<Vec<()>>>()?;
         }
 
         Ok(())
     }
+
+    // adds the pull that came in to the session it's part of & edits its
+    // message, or sends one if it's new
+    async fn update_live(
+        sender: &Arc<Mutex<S>>,
+        options: &Options,
+        log_dir: &Path,
+        update: LiveUpdate<'_>,
+        msg_gen: &Arc<M>,
+        live: &Live,
+    ) -> Result<()> {
+        // sessions don't go past a reset, so nothing before it is needed
+        let log = update.pull.log.clone();
+        let reset = (update.pull.start / 1000).div_euclid(24 * 60 * 60) * 24 * 60 * 60;
+        let pulls = live.add_pull(reset, update.pull, || {
+            find_pulls_since(log_dir, Some(reset))
+        })?;
+        let session = session::group(pulls, live.gap as i64 * 60 * 1000)
+            .into_iter()
+            .find(|session| {
+                session
+                    .pulls
+                    .iter()
+                    .any(|pull| pull.log.path() == log.path())
+            })
+            .ok_or(Error::NoRecentLog)?;
+
+        let kills = session.kills();
+        let uploaded_logs: Vec<UploadedLog> = kills
+            .iter()
+            .map(|pull| {
+                let link = options.history.as_ref().and_then(|h| h.link(&pull.log));
+                UploadedLog::new(pull.log.clone(), link).with_record(&pull.record)
+            })
+            .collect();
+        let log_infos: Vec<LogInfo> = uploaded_logs
+            .iter()
+            .zip(&kills)
+            .flat_map(|(uploaded_log, pull)| {
+                pull.encounters
+                    .iter()
+                    .cloned()
+                    .zip(compare(options, &pull.record))
+                    .map(move |(e, c)| LogInfo::new(uploaded_log, e).with_comparison(c))
+            })
+            .collect();
+
+        // held throughout so two logs arriving together don't both send a
+        // new message
+        let mut sender = sender.lock().await;
+        let id = live.message(session.start());
+        let id = sender
+            .send_live(
+                &**msg_gen,
+                &session,
+                &log_infos,
+                update.new,
+                update.files,
+                id.as_deref(),
+            )
+            .await?;
+        if let Some(id) = id {
+            live.set(session.start(), id);
+        }
+        Ok(())
+    }
+
+    async fn drain_queue(
+        sender: &Arc<Mutex<S>>,
+        options: &Options,
+        cache: &C,
+        log_dir: &Path,
+        msg_gen: &Arc<M>,
+    ) {
+        let queue = match &options.queue {
+            Some(queue) => queue,
+            None => return,
+        };
+
+        for item in queue.due(unix_now()) {
+            log::info!("retrying queued {}", item.job.path().display());
+            let res =
+                Self::handle_queued(sender, options, cache, log_dir, msg_gen, queue, &item.job)
+                    .await;
+            match res {
+                Ok(()) => queue.complete(&item.job),
+                Err(e) => {
+                    log::warn!("queued {} failed again: {}", item.job.path().display(), e);
+                    queue.fail(&item.job, &e, &options.retry, unix_now());
+                }
+            }
+        }
+    }
+
+    async fn handle_queued(
+        sender: &Arc<Mutex<S>>,
+        options: &Options,
+        cache: &C,
+        log_dir: &Path,
+        msg_gen: &Arc<M>,
+        queue: &Queue,
+        job: &Job,
+    ) -> Result<()> {
+        let path = job.path().to_owned();
+        let log =
+            Log::from_file_checked(&path).ok_or_else(|| Error::UnsupportedLog(path.clone()))?;
+        let (encounters, data) =
+            parse::parse(&log).ok_or_else(|| Error::UnsupportedLog(path.clone()))?;
+
+        let uploaded_log = match job {
+            Job::Upload { .. } => {
+                let uploaded_log = upload(options, log).await?;
+                cache.insert(&uploaded_log);
+                let link = uploaded_log.link.clone();
+                queue.advance(Job::Message { path, link });
+                uploaded_log
+            }
+            Job::Message { link, .. } => UploadedLog::new(log, link.clone()),
+        };
+
+        Self::announce(
+            sender,
+            options,
+            log_dir,
+            &uploaded_log,
+            encounters,
+            &data,
+            msg_gen,
+        )
+        .await
+    }
 }
 
-async fn find_recent_log(log_dir: impl AsRef<Path>, target: Target) -> Result<Log> {
-    log::trace!("finding most recent `{}` log", target);
+async fn upload(options: &Options, log: Log) -> Result<UploadedLog> {
+    if options.dry_run {
+        log::info!("dry run: not uploading log: {}", log);
+        let link = upload::local_link(log.path());
+        return Ok(UploadedLog::new(log, Some(link)));
+    }
+
+    log::info!("uploading log: {}", log);
+    let res = options
+        .retry
+        .run("uploading log", || async {
+            // only held while actually uploading, not while waiting to retry
+            let _permit = options.uploads.acquire().await;
+            options.uploader.upload(log.path()).await
+        })
+        .await;
+    match res {
+        Ok(Some(res)) => {
+            let link = res.permalink.clone();
+            Ok(UploadedLog::new(log, Some(link)).with_response(res))
+        }
+        Ok(None) => Ok(UploadedLog::new(log, None)),
+        // it doesn't end up in the history with a link, so a backfill will
+        // still pick it up later
+        Err(e) if options.failover => {
+            log::warn!("failed to upload {}, going on without a link: {}", log, e);
+            Ok(UploadedLog::new(log, None))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// how each kill in `record` compares to the ones recorded before it, in the
+// same order as its encounters
+fn compare(options: &Options, record: &Record) -> Vec<Option<Comparison>> {
+    record
+        .encounters
+        .iter()
+        .map(|encounter| {
+            let history = options.history.as_ref()?;
+            history.compare(record, encounter)
+        })
+        .collect()
+}
+
+// uploaded logs are kept track of for reports, unless it was only a dry run
+fn remember(options: &Options, log: &Log, record: Record) {
+    if options.dry_run {
+        return;
+    }
+
+    if let Some(history) = &options.history {
+        history.insert(log, record);
+    }
+}
+
+// the most recent successful kill of `target` within `window`, or the attempt
+// that got the furthest if there wasn't one
+async fn find_recent_log(log_dir: impl AsRef<Path>, target: Target, window: Window) -> Result<Log> {
     let dir_path: PathBuf = log_dir.as_ref().join(target.dir_name());
-    let mut newest_log = PathBuf::new();
-    visit_dir(dir_path, &mut newest_log)?;
-    Log::from_file_checked(&newest_log).ok_or(Error::NoRecentLog)
+    // parsing can take a while, so it's kept off the runtime
+    tokio::task::spawn_blocking(move || find_recent_log_in(dir_path, target, window))
+        .await
+        .expect("finding recent log panicked")
+}
+
+fn find_recent_log_in(dir_path: PathBuf, target: Target, window: Window) -> Result<Log> {
+    log::trace!("finding most recent `{}` log", target);
+    let mut paths = Vec::new();
+    collect_logs(dir_path, &mut paths)?;
+    // newest first, so the search can stop at the first kill
+    paths.sort_by(|a, b| b.file_stem().cmp(&a.file_stem()));
+
+    let window_start = window.start(unix_now());
+    let mut best_attempt: Option<(Log, (usize, Time))> = None;
+    for log in paths.into_iter().filter_map(Log::from_file_checked) {
+        let (encounters, data) = match parse::parse(&log) {
+            Some(parsed) => parsed,
+            None => {
+                log::debug!("skipping unparseable log `{}`", log);
+                continue;
+            }
+        };
+
+        let started = log_start(&log, &data);
+        if let (Some(started), Some(window_start)) = (started, window_start) {
+            if started < window_start {
+                log::trace!("`{}` is outside of the {} window", log, window);
+                break;
+            }
+        }
+
+        if !encounters.is_empty() && encounters.iter().all(|e| e.success) {
+            log::debug!("found kill `{}`", log);
+            return Ok(log);
+        }
+
+        // no health information to go by, so furthest phase then longest
+        let progress = encounters
+            .iter()
+            .map(|e| (e.phases.len(), e.phases[0].duration()))
+            .max()
+            .unwrap_or_default();
+        if best_attempt
+            .as_ref()
+            .is_none_or(|(_, best)| progress > *best)
+        {
+            best_attempt = Some((log, progress));
+        }
+    }
+
+    let (log, _) = best_attempt.ok_or(Error::NoRecentLog)?;
+    log::debug!("no kill found: using best attempt `{}`", log);
+    Ok(log)
+}
+
+// every fractal pull within `window`, newest logs are parsed first so the
+// rest of the archive doesn't need to be touched
+fn find_pulls(log_dir: &Path, window: Window) -> Result<Vec<Pull>> {
+    find_pulls_since(log_dir, window.start(unix_now()))
+}
+
+// `since` is a unix timestamp, nothing set means every log
+fn find_pulls_since(log_dir: &Path, since: Option<i64>) -> Result<Vec<Pull>> {
+    let mut paths = Vec::new();
+    collect_logs(log_dir.to_owned(), &mut paths)?;
+    paths.sort_by(|a, b| b.file_stem().cmp(&a.file_stem()));
+
+    let mut pulls = Vec::new();
+    for log in paths.into_iter().filter_map(Log::from_file_checked) {
+        if log.target().fractal().is_none() {
+            continue;
+        }
+
+        let (encounters, data) = match parse::parse(&log) {
+            Some(parsed) => parsed,
+            None => {
+                log::debug!("skipping unparseable log `{}`", log);
+                continue;
+            }
+        };
+
+        let started = match log_start(&log, &data) {
+            Some(started) => started,
+            None => continue,
+        };
+        if since.is_some_and(|since| started < since) {
+            break;
+        }
+
+        let record = Record::new(&log, None, &encounters, &data);
+        pulls.push(Pull::new(log, encounters, record, started * 1000));
+    }
+
+    Ok(pulls)
 }
 
-fn visit_dir(path: PathBuf, current: &mut PathBuf) -> Result<()> {
-    let dir = fs::read_dir(path)?;
+// unix timestamp of when `log` was started, if the log doesn't say then the
+// file's modification time is close enough
+fn log_start(log: &Log, data: &parse::Data) -> Option<i64> {
+    data.meta.log_start.or_else(|| {
+        let modified = fs::metadata(log.path()).ok()?.modified().ok()?;
+        let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+        Some(since_epoch.as_secs() as i64)
+    })
+}
+
+// logs that should go along with the message about them
+fn attachments<'a>(
+    options: &Options,
+    uploaded_logs: impl IntoIterator<Item = &'a UploadedLog>,
+) -> Vec<PathBuf> {
+    if !options.attach_logs {
+        return Vec::new();
+    }
 
-    for f in dir {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for uploaded_log in uploaded_logs {
+        let path = uploaded_log.log.path();
+        if uploaded_log.link.is_none() && !files.iter().any(|file| file == path) {
+            files.push(path.to_owned());
+        }
+    }
+    files
+}
+
+fn enqueue(options: &Options, job: Job, error: &Error) {
+    if options.dry_run {
+        return;
+    }
+
+    if let Some(queue) = &options.queue {
+        queue.push(job, error, unix_now());
+    }
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+// every supported log in `log_dir` that `backfill` applies to, oldest first
+fn find_logs(log_dir: &Path, backfill: &Backfill) -> Result<Vec<Log>> {
+    let mut paths = Vec::new();
+    collect_logs(log_dir.to_owned(), &mut paths)?;
+
+    // checking the date first avoids reading the header of every old log
+    if let Some(since) = backfill.since {
+        paths.retain(|path| log_date(path).is_some_and(|date| date >= since));
+    }
+    paths.sort_by(|a, b| a.file_stem().cmp(&b.file_stem()));
+
+    Ok(paths
+        .into_iter()
+        .filter_map(Log::from_file_checked)
+        .filter(|log| backfill.targets.is_empty() || backfill.targets.contains(&log.target()))
+        .collect())
+}
+
+fn collect_logs(path: PathBuf, logs: &mut Vec<PathBuf>) -> Result<()> {
+    for f in fs::read_dir(path)? {
         let f = f?;
         let path = f.path();
-        let current_stem = current
-            .file_stem()
-            .unwrap_or_else(|| std::ffi::OsStr::new(""));
         if f.file_type()?.is_file() {
-            let stem = path.file_stem().expect("found file with no name");
-            if current_stem.cmp(stem) == std::cmp::Ordering::Less {
-                *current = path;
+            if Log::validate(&path) {
+                logs.push(path);
             }
         } else {
-            visit_dir(path, current)?;
+            collect_logs(path, logs)?;
         }
     }
 
     Ok(())
 }
 
+// arcdps names logs after the local time they started at, i.e. 20200407-174541
+fn log_date(path: &Path) -> Option<NaiveDate> {
+    let stem = path.file_stem()?.to_str()?;
+    NaiveDate::parse_from_str(stem.get(..8)?, "%Y%m%d").ok()
+}
+
 #[derive(Clone)]
 pub struct UploadedLog {
     pub log: Log,
-    pub link: String,
+    // missing if it wasn't uploaded anywhere
+    pub link: Option<String>,
+    // what dps.report made of the log, missing if it came from a cache
+    pub response: Option<upload::Response>,
+    // unix timestamp of when the log started, if it says
+    pub start: Option<i64>,
+    pub players: Vec<String>,
+    pub specs: BTreeMap<String, Spec>,
+    pub gw2_build: Option<u64>,
+    pub recorder: Option<String>,
+    // what each player did to the boss, most first
+    pub damage: Vec<DamageRecord>,
+    pub health: Vec<HealthRecord>,
 }
 
 impl UploadedLog {
-    pub const fn new(log: Log, link: String) -> Self {
-        Self { log, link }
+    pub const fn new(log: Log, link: Option<String>) -> Self {
+        Self {
+            log,
+            link,
+            response: None,
+            start: None,
+            players: Vec::new(),
+            specs: BTreeMap::new(),
+            gw2_build: None,
+            recorder: None,
+            damage: Vec::new(),
+            health: Vec::new(),
+        }
+    }
+
+    pub fn with_response(mut self, response: upload::Response) -> Self {
+        self.response = Some(response);
+        self
+    }
+
+    // what parsing the log found out about it
+    pub fn with_record(mut self, record: &Record) -> Self {
+        self.start = record.start;
+        self.players = record.players.clone();
+        self.specs = record.specs.clone();
+        self.gw2_build = record.gw2_build;
+        self.recorder = record.recorder.clone();
+        self.damage = record.damage.clone();
+        self.health = record.health.clone();
+        self
     }
 }
 
+#[derive(Clone)]
 pub struct LogInfo<'a> {
     pub log: &'a UploadedLog,
     pub encounter: Encounter,
+    pub comparison: Option<Comparison>,
+    // drawn to go along with the message about the encounter
+    pub chart: Option<PathBuf>,
 }
 
 impl<'a> LogInfo<'a> {
     pub const fn new(log: &'a UploadedLog, encounter: Encounter) -> Self {
-        Self { log, encounter }
+        Self {
+            log,
+            encounter,
+            comparison: None,
+            chart: None,
+        }
+    }
+
+    pub fn with_comparison(mut self, comparison: Option<Comparison>) -> Self {
+        self.comparison = comparison;
+        self
+    }
+
+    // if charts are wanted
+    pub fn with_chart(mut self, options: &Options) -> Self {
+        if options.charts.enabled {
+            self.chart = options
+                .charts
+                .draw(&self, &options.templates.squad)
+                .map_err(|e| log::warn!("failed to draw chart: {}", e))
+                .ok();
+        }
+        self
     }
 }
 
@@ -343,10 +1474,42 @@ mod tests {
         ];
 
         for (target, expected) in data.into_iter() {
-            let recent_log = super::find_recent_log("tests/example_logs", target)
-                .await
-                .unwrap();
+            let recent_log =
+                super::find_recent_log("tests/example_logs", target, super::Window::All)
+                    .await
+                    .unwrap();
             assert_eq!(expected, recent_log.path().file_stem().unwrap());
         }
     }
+
+    #[test]
+    fn parses_windows() {
+        use super::Window;
+
+        assert_eq!(Ok(Window::Reset), "reset".parse());
+        assert_eq!(Ok(Window::All), "all".parse());
+        assert_eq!(Ok(Window::Hours(12)), "12h".parse());
+        assert!("12".parse::<Window>().is_err());
+        assert!("-1h".parse::<Window>().is_err());
+        assert!("h".parse::<Window>().is_err());
+        assert!("twelveh".parse::<Window>().is_err());
+        for window in &["reset", "all", "36h"] {
+            assert_eq!(*window, window.parse::<Window>().unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn window_starts() {
+        use super::Window;
+
+        let now = 1_600_000_000;
+        assert_eq!(Some(1_599_955_200), Window::Reset.start(now));
+        assert_eq!(Some(now - 2 * 60 * 60), Window::Hours(2).start(now));
+        assert_eq!(None, Window::All.start(now));
+        assert_eq!(None, Window::Hours(u64::MAX).start(now));
+        assert_eq!(
+            Some(i64::MIN),
+            Window::Hours(i64::MAX as u64 / 3600).start(i64::MIN + 1)
+        );
+    }
 }
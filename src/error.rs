@@ -1,4 +1,6 @@
 use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -6,9 +8,61 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
     Bincode(bincode::Error),
     Io(std::io::Error),
+    Json(serde_json::Error),
     Reqwest(reqwest::Error),
+    // unsuccessful response, with how long to wait before trying again if the
+    // server said so
+    Status(reqwest::StatusCode, Option<Duration>),
+    Toml(toml::de::Error),
+    TomlEdit(toml_edit::TomlError),
     LogDirectory,
     NoRecentLog,
+    UnsupportedLog(PathBuf),
+    MissingReport(PathBuf),
+    // the uploader answered, but wouldn't take the log
+    RejectedLog(PathBuf),
+    UnknownWebhook(String),
+    UnknownQueueItem,
+    InvalidWebhookUrl(String),
+    // something other than the url is missing or wrong
+    InvalidWebhook(String),
+}
+
+impl Error {
+    // whether trying again later might help, i.e. timeouts, rate limits and
+    // server errors as opposed to missing files or rejected logs
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Reqwest(e) => {
+                e.is_timeout()
+                    || e.is_request()
+                    || e.is_body()
+                    || e.status().is_some_and(is_transient_status)
+            }
+            Self::Status(status, _) => is_transient_status(*status),
+            Self::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+            ),
+            _ => false,
+        }
+    }
+
+    pub const fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Status(_, retry_after) => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+fn is_transient_status(status: reqwest::StatusCode) -> bool {
+    status.is_server_error()
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
 }
 
 impl From<bincode::Error> for Error {
@@ -23,20 +77,55 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
 impl From<reqwest::Error> for Error {
     fn from(e: reqwest::Error) -> Self {
         Self::Reqwest(e)
     }
 }
 
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Self::Toml(e)
+    }
+}
+
+impl From<toml_edit::TomlError> for Error {
+    fn from(e: toml_edit::TomlError) -> Self {
+        Self::TomlEdit(e)
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Bincode(e) => write!(f, "{}", e),
             Self::Io(e) => write!(f, "{}", e),
+            Self::Json(e) => write!(f, "{}", e),
             Self::Reqwest(e) => write!(f, "{}", e),
+            Self::Status(status, _) => write!(f, "server responded with {}", status),
+            Self::Toml(e) => write!(f, "config error: {}", e),
+            Self::TomlEdit(e) => write!(f, "unable to update config: {}", e),
             Self::LogDirectory => write!(f, "log directory error"),
             Self::NoRecentLog => write!(f, "unable to find recent log"),
+            Self::UnsupportedLog(path) => write!(f, "unsupported log: {}", path.display()),
+            Self::MissingReport(path) => {
+                write!(f, "no Elite Insights report found for {}", path.display())
+            }
+            Self::RejectedLog(path) => write!(f, "upload rejected: {}", path.display()),
+            Self::UnknownWebhook(name) => write!(
+                f,
+                "no such webhook: `{}`, try adding it with `./snek add <name> <url>`",
+                name
+            ),
+            Self::UnknownQueueItem => write!(f, "no such queued item"),
+            Self::InvalidWebhookUrl(url) => write!(f, "invalid webhook url: {}", url),
+            Self::InvalidWebhook(reason) => write!(f, "invalid webhook: {}", reason),
         }
     }
 }
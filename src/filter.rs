@@ -1,4 +1,8 @@
-use crate::parse::Encounter;
+use crate::{core::LogInfo, parse::Encounter};
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
 
 pub trait Filter {
     fn filter(&self, log: &Encounter) -> bool;
@@ -20,3 +24,59 @@ impl Filter for Unsuccessful {
         !log.success
     }
 }
+
+// which logs a destination wants to hear about
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Only {
+    #[default]
+    All,
+    Kills,
+    // kills faster than any recorded before them
+    Records,
+    Wipes,
+}
+
+impl Only {
+    pub fn wants(self, log: &LogInfo) -> bool {
+        let success = log.encounter.success;
+        match self {
+            Self::All => true,
+            Self::Kills => success,
+            Self::Records => success && log.comparison.as_ref().is_some_and(|c| c.is_best()),
+            Self::Wipes => !success,
+        }
+    }
+
+    pub fn is_all(&self) -> bool {
+        *self == Self::All
+    }
+}
+
+impl FromStr for Only {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            "kills" => Ok(Self::Kills),
+            "records" => Ok(Self::Records),
+            "wipes" => Ok(Self::Wipes),
+            _ => Err(format!(
+                "unknown filter `{}`, expected all, kills, records or wipes",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Only {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => write!(f, "all"),
+            Self::Kills => write!(f, "kills"),
+            Self::Records => write!(f, "records"),
+            Self::Wipes => write!(f, "wipes"),
+        }
+    }
+}
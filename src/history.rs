@@ -0,0 +1,721 @@
+use crate::{
+    cache::Cache,
+    error::Result,
+    log::Log,
+    message::{fmt_time2, fmt_time3},
+    parse::{Data, Encounter, Time},
+    spec::Spec,
+    target::Target,
+};
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PATH: &str = "history.json";
+
+// everything worth remembering about a log after it's been processed, new
+// fields need a default so older history files keep loading
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub target: Target,
+    pub file: String,
+    #[serde(default)]
+    pub link: Option<String>,
+    // unix timestamp according to the server
+    #[serde(default)]
+    pub start: Option<i64>,
+    #[serde(default)]
+    pub players: Vec<String>,
+    // what each player played, if the log says
+    #[serde(default)]
+    pub specs: BTreeMap<String, Spec>,
+    pub encounters: Vec<EncounterRecord>,
+    #[serde(default)]
+    pub deaths: Vec<DeathRecord>,
+    #[serde(default)]
+    pub gw2_build: Option<u64>,
+    // account name of whoever recorded the log
+    #[serde(default)]
+    pub recorder: Option<String>,
+    // what each player did to the boss, most first
+    #[serde(default)]
+    pub damage: Vec<DamageRecord>,
+    // how much health the boss had left over time
+    #[serde(default)]
+    pub health: Vec<HealthRecord>,
+    // kills of the same target get compared against this one instead of the pb
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncounterRecord {
+    pub target: Target,
+    pub success: bool,
+    pub duration: Time,
+    #[serde(default)]
+    pub phases: Vec<PhaseRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseRecord {
+    pub name: Option<String>,
+    pub duration: Time,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DamageRecord {
+    pub player: String,
+    pub damage: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthRecord {
+    pub time: Time,
+    pub percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeathRecord {
+    pub player: String,
+    pub skill: Option<String>,
+    pub time: Time,
+}
+
+impl Record {
+    pub fn new(log: &Log, link: Option<&str>, encounters: &[Encounter], data: &Data) -> Self {
+        let encounters = encounters
+            .iter()
+            .map(|encounter| EncounterRecord {
+                target: encounter.target,
+                success: encounter.success,
+                duration: encounter.phases[0].duration(),
+                phases: encounter
+                    .phases
+                    .iter()
+                    .skip(1)
+                    .map(|phase| PhaseRecord {
+                        name: phase.name.clone(),
+                        duration: phase.duration(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let mut players: Vec<String> = data.players.values().map(|p| p.name.clone()).collect();
+        players.sort();
+        let specs = data
+            .players
+            .values()
+            .filter_map(|p| Some((p.name.clone(), p.spec?)))
+            .collect();
+
+        let deaths = data
+            .player_deaths()
+            .into_iter()
+            .map(|death| DeathRecord {
+                player: death.player,
+                skill: death.skill,
+                time: death.time,
+            })
+            .collect();
+
+        let damage = data
+            .player_damage()
+            .into_iter()
+            .map(|(player, damage)| DamageRecord { player, damage })
+            .collect();
+
+        let health = data
+            .boss_health()
+            .into_iter()
+            .map(|(time, percent)| HealthRecord { time, percent })
+            .collect();
+
+        Self {
+            target: log.target(),
+            file: log.file_name(),
+            link: link.map(str::to_owned),
+            start: data.meta.log_start,
+            players,
+            specs,
+            encounters,
+            deaths,
+            gw2_build: data.meta.gw2_build,
+            recorder: data.meta.pov.clone(),
+            damage,
+            health,
+            pinned: false,
+        }
+    }
+}
+
+// the fastest recorded kill of a target, either by anyone or a particular squad
+#[derive(Debug, Clone, Serialize)]
+pub struct Best {
+    pub target: Target,
+    pub duration: Time,
+    pub file: String,
+    pub link: Option<String>,
+    pub start: Option<i64>,
+    pub players: Vec<String>,
+    // fastest of each phase over every kill with the same number of phases,
+    // so not necessarily from the fastest kill
+    pub splits: Vec<Time>,
+}
+
+impl Best {
+    pub fn sum_of_best(&self) -> Option<Time> {
+        if self.splits.is_empty() {
+            None
+        } else {
+            Some(self.splits.iter().sum())
+        }
+    }
+}
+
+// how a kill stacks up against the bests recorded before it
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub duration: Time,
+    pub best: Option<Time>,
+    pub squad_best: Option<Time>,
+    // phase by phase against the pinned kill, or the pb if nothing is pinned
+    pub reference: Option<Reference>,
+    // fastest of each phase over every kill up to and including this one
+    pub sum_of_best: Option<Time>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub pinned: bool,
+    pub file: String,
+    pub splits: Vec<Split>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Split {
+    pub name: Option<String>,
+    pub duration: Time,
+    // missing if the reference has fewer phases
+    pub reference: Option<Time>,
+}
+
+impl Split {
+    // negative if faster
+    pub fn delta(&self) -> Option<i64> {
+        self.reference
+            .map(|reference| self.duration as i64 - reference as i64)
+    }
+}
+
+impl Reference {
+    fn new(
+        pinned: bool,
+        record: &Record,
+        kill: &EncounterRecord,
+        encounter: &EncounterRecord,
+    ) -> Self {
+        let splits = encounter
+            .phases
+            .iter()
+            .enumerate()
+            .map(|(idx, phase)| Split {
+                name: phase.name.clone(),
+                duration: phase.duration,
+                reference: kill.phases.get(idx).map(|phase| phase.duration),
+            })
+            .collect();
+
+        Self {
+            pinned,
+            file: record.file.clone(),
+            splits,
+        }
+    }
+}
+
+impl Comparison {
+    pub fn is_best(&self) -> bool {
+        self.best.is_none_or(|best| self.duration < best)
+    }
+
+    pub fn is_squad_best(&self) -> bool {
+        self.squad_best.is_none_or(|best| self.duration < best)
+    }
+
+    // negative if faster
+    pub fn delta(&self) -> Option<i64> {
+        self.best.map(|best| self.duration as i64 - best as i64)
+    }
+
+    pub fn squad_delta(&self) -> Option<i64> {
+        self.squad_best
+            .map(|best| self.duration as i64 - best as i64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct History {
+    cache: Arc<Mutex<Cache<String, Record>>>,
+}
+
+impl History {
+    pub async fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let cache = Arc::new(Mutex::new(Cache::new(path).await?));
+        Ok(Self { cache })
+    }
+
+    // file names are timestamps, so they only need the target to be unique
+    fn key(log: &Log) -> String {
+        format!("{:?}/{}", log.target(), log.id())
+    }
+
+    // whether `log` has already been uploaded
+    pub fn contains(&self, log: &Log) -> bool {
+        self.cache
+            .lock()
+            .unwrap()
+            .get(&Self::key(log))
+            .is_some_and(|record| record.link.is_some())
+    }
+
+    // where `log` was uploaded to, if it was
+    pub fn link(&self, log: &Log) -> Option<String> {
+        self.cache
+            .lock()
+            .unwrap()
+            .get(&Self::key(log))
+            .and_then(|record| record.link.clone())
+    }
+
+    // saved right away, watching only ever ends by being killed
+    fn update<T>(&self, f: impl FnOnce(&mut Cache<String, Record>) -> T) -> T {
+        let mut cache = self.cache.lock().unwrap();
+        let res = f(&mut cache);
+        if let Err(e) = cache.save() {
+            log::error!("failed to save history: {}", e);
+        }
+        res
+    }
+
+    pub fn insert(&self, log: &Log, mut record: Record) {
+        let key = Self::key(log);
+        self.update(|cache| {
+            // re-uploading a log shouldn't unpin it
+            record.pinned |= cache.get(&key).is_some_and(|old| old.pinned);
+            cache.insert(key, record);
+        });
+    }
+
+    // makes `record` the reference for its target, replacing any earlier pin
+    pub fn pin(&self, log: &Log, mut record: Record) {
+        let key = Self::key(log);
+        self.update(|cache| {
+            Self::unpin_in(cache, record.target);
+            if record.link.is_none() {
+                record.link = cache.get(&key).and_then(|old| old.link.clone());
+            }
+            record.pinned = true;
+            cache.insert(key, record);
+        });
+    }
+
+    // false if nothing was pinned for `target`
+    pub fn unpin(&self, target: Target) -> bool {
+        self.update(|cache| Self::unpin_in(cache, target))
+    }
+
+    fn unpin_in(cache: &mut Cache<String, Record>, target: Target) -> bool {
+        let pinned: Vec<(String, Record)> = cache
+            .raw()
+            .iter()
+            .filter(|(_, record)| record.pinned && record.target == target)
+            .map(|(key, record)| (key.clone(), record.clone()))
+            .collect();
+
+        let unpinned = !pinned.is_empty();
+        for (key, mut record) in pinned {
+            record.pinned = false;
+            cache.insert(key, record);
+        }
+        unpinned
+    }
+
+    pub fn pinned(&self, target: Target) -> Option<Record> {
+        self.cache
+            .lock()
+            .unwrap()
+            .raw()
+            .values()
+            .find(|record| record.pinned && record.target == target)
+            .cloned()
+    }
+
+    // oldest first
+    pub fn records(&self) -> Vec<Record> {
+        let cache = self.cache.lock().unwrap();
+        let mut records: Vec<Record> = cache.raw().values().cloned().collect();
+        records.sort_by(|a, b| (a.start, &a.file).cmp(&(b.start, &b.file)));
+        records
+    }
+
+    // one best per target, or per target and squad if `by_squad`
+    pub fn bests(&self, by_squad: bool) -> Vec<Best> {
+        let records = self.records();
+        let mut kills: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (record, encounter) in successful(&records) {
+            let squad = if by_squad {
+                record.players.clone()
+            } else {
+                Vec::new()
+            };
+            kills
+                .entry((encounter.target, squad))
+                .or_default()
+                .push((record, encounter));
+        }
+
+        kills
+            .into_values()
+            .filter_map(|kills| best_of(kills.into_iter()))
+            .collect()
+    }
+
+    // compares `encounter` of `record` against every kill recorded before it
+    pub fn compare(&self, record: &Record, encounter: &EncounterRecord) -> Option<Comparison> {
+        if !encounter.success {
+            return None;
+        }
+
+        let cache = self.cache.lock().unwrap();
+        let kills: Vec<_> = cache
+            .raw()
+            .values()
+            .filter(|other| other.file != record.file)
+            .flat_map(successful_in)
+            .filter(|(_, kill)| kill.target == encounter.target)
+            .collect();
+        let earlier: Vec<_> = kills
+            .iter()
+            .filter(|(other, _)| match record.start {
+                Some(start) => other.start.is_some_and(|other_start| other_start < start),
+                None => true,
+            })
+            .collect();
+
+        let best_kill = earlier.iter().min_by_key(|(_, kill)| kill.duration);
+        let squad_best = earlier
+            .iter()
+            .filter(|(other, _)| other.players == record.players)
+            .map(|(_, kill)| kill.duration)
+            .min();
+
+        // a pinned kill is always a valid reference, even if it happened later
+        let reference = match kills.iter().find(|(other, _)| other.pinned) {
+            Some((other, kill)) => Some(Reference::new(true, other, kill, encounter)),
+            None => best_kill.map(|(other, kill)| Reference::new(false, other, kill, encounter)),
+        };
+
+        let phases = encounter.phases.len();
+        let kills = earlier
+            .iter()
+            .map(|(_, kill)| *kill)
+            .chain(std::iter::once(encounter));
+        let sum_of_best = if phases == 0 {
+            None
+        } else {
+            Some(best_splits(kills, phases).iter().sum())
+        };
+
+        Some(Comparison {
+            duration: encounter.duration,
+            best: best_kill.map(|(_, kill)| kill.duration),
+            squad_best,
+            reference,
+            sum_of_best,
+        })
+    }
+}
+
+fn successful_in(record: &Record) -> impl Iterator<Item = (&Record, &EncounterRecord)> {
+    record
+        .encounters
+        .iter()
+        .filter(|encounter| encounter.success)
+        .map(move |encounter| (record, encounter))
+}
+
+fn successful(records: &[Record]) -> impl Iterator<Item = (&Record, &EncounterRecord)> {
+    records.iter().flat_map(successful_in)
+}
+
+fn best_of<'a>(
+    kills: impl Iterator<Item = (&'a Record, &'a EncounterRecord)> + Clone,
+) -> Option<Best> {
+    let (record, encounter) = kills
+        .clone()
+        .min_by_key(|(record, encounter)| (encounter.duration, record.start))?;
+
+    let splits = best_splits(kills.map(|(_, kill)| kill), encounter.phases.len());
+
+    Some(Best {
+        target: encounter.target,
+        duration: encounter.duration,
+        file: record.file.clone(),
+        link: record.link.clone(),
+        start: record.start,
+        players: record.players.clone(),
+        splits,
+    })
+}
+
+// fastest of each phase over the kills with exactly `phases` phases, kills
+// missing a phase can't be lined up with the rest so they're left out.
+// nothing if there aren't any such kills
+fn best_splits<'a>(kills: impl Iterator<Item = &'a EncounterRecord>, phases: usize) -> Vec<Time> {
+    kills
+        .filter(|kill| kill.phases.len() == phases)
+        .map(|kill| kill.phases.iter().map(|phase| phase.duration).collect())
+        .reduce(|best: Vec<Time>, splits| {
+            best.iter()
+                .zip(splits)
+                .map(|(best, split)| split.min(*best))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl Display for Best {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} - {}",
+            self.target,
+            fmt_time3(self.duration),
+            self.file
+        )?;
+        if let Some(link) = &self.link {
+            write!(f, " {}", link)?;
+        }
+        if !self.players.is_empty() {
+            write!(f, "\n    squad: {}", self.players.join(", "))?;
+        }
+        if !self.splits.is_empty() {
+            let splits: Vec<String> = self
+                .splits
+                .iter()
+                .enumerate()
+                .map(|(idx, split)| format!("{}: {}", idx + 1, fmt_time2(*split)))
+                .collect();
+            write!(f, "\n    best splits: {}", splits.join(" - "))?;
+        }
+        if let Some(sum) = self.sum_of_best() {
+            write!(f, "\n    sum of best: {}", fmt_time3(sum))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a history nothing has been recorded in yet
+    async fn history(name: &str) -> History {
+        let path = std::env::temp_dir().join(format!("snek-{}.json", name));
+        let _ = std::fs::remove_file(&path);
+        History::new(path).await.unwrap()
+    }
+
+    fn record(file: &str, start: i64, players: &[&str], success: bool, phases: &[Time]) -> Record {
+        serde_json::from_value(serde_json::json!({
+            "target": Target::Skor,
+            "file": file,
+            "link": format!("https://dps.report/{}", file),
+            "start": start,
+            "players": players,
+            "encounters": [{
+                "target": Target::Skor,
+                "success": success,
+                "duration": phases.iter().sum::<Time>(),
+                "phases": phases
+                    .iter()
+                    .map(|duration| serde_json::json!({"name": null, "duration": duration}))
+                    .collect::<Vec<_>>(),
+            }],
+        }))
+        .unwrap()
+    }
+
+    fn insert(history: &History, record: &Record) {
+        let log = Log::new(record.target, format!("{}.zevtc", record.file));
+        history.insert(&log, record.clone());
+    }
+
+    #[tokio::test]
+    async fn keeps_records_across_reloads() {
+        let history = history("history-reload").await;
+        let kill = record("a", 1, &["x", "y"], true, &[30, 40]);
+        let pin = record("b", 2, &["x", "y"], true, &[35, 40]);
+        insert(&history, &kill);
+        history.pin(&Log::new(Target::Skor, "b.zevtc"), pin);
+
+        let path = std::env::temp_dir().join("snek-history-reload.json");
+        let reloaded = History::new(&path).await.unwrap();
+        assert!(reloaded.contains(&Log::new(Target::Skor, "a.zevtc")));
+        assert_eq!(
+            Some("b"),
+            reloaded
+                .pinned(Target::Skor)
+                .as_ref()
+                .map(|r| r.file.as_str())
+        );
+
+        assert!(history.unpin(Target::Skor));
+        let reloaded = History::new(&path).await.unwrap();
+        assert!(reloaded.pinned(Target::Skor).is_none());
+    }
+
+    #[tokio::test]
+    async fn finds_bests() {
+        let history = history("finds-bests").await;
+        assert!(history.bests(false).is_empty());
+
+        let records = [
+            record("a", 1, &["x", "y"], true, &[30, 40]),
+            record("b", 2, &["x", "z"], true, &[20, 40]),
+            record("c", 3, &["x", "y"], true, &[35, 30]),
+            record("d", 4, &["x", "y"], false, &[10]),
+        ];
+        for record in &records {
+            insert(&history, record);
+        }
+
+        let bests = history.bests(false);
+        assert_eq!(1, bests.len());
+        assert_eq!(("b", 60), (bests[0].file.as_str(), bests[0].duration));
+        assert_eq!(vec![20, 30], bests[0].splits);
+        assert_eq!(Some(50), bests[0].sum_of_best());
+
+        let mut bests: Vec<(Vec<String>, String, Time)> = history
+            .bests(true)
+            .into_iter()
+            .map(|best| (best.players, best.file, best.duration))
+            .collect();
+        bests.sort();
+        assert_eq!(
+            vec![
+                (vec!["x".into(), "y".into()], "c".into(), 65),
+                (vec!["x".into(), "z".into()], "b".into(), 60),
+            ],
+            bests
+        );
+    }
+
+    #[tokio::test]
+    async fn compares_against_earlier_kills() {
+        let history = history("compares").await;
+        let first = record("a", 10, &["x", "y"], true, &[30, 40]);
+        assert!(history
+            .compare(&first, &first.encounters[0])
+            .is_some_and(|comparison| comparison.is_best()
+                && comparison.is_squad_best()
+                && comparison.reference.is_none()
+                && comparison.sum_of_best == Some(70)));
+
+        insert(&history, &first);
+        insert(&history, &record("b", 20, &["x", "z"], true, &[20, 45]));
+        insert(&history, &record("c", 30, &["x", "z"], false, &[5]));
+        // happened after the kill being compared, so doesn't count
+        insert(&history, &record("e", 50, &["x", "y"], true, &[10, 10]));
+
+        let kill = record("d", 40, &["x", "y"], true, &[25, 42]);
+        let comparison = history.compare(&kill, &kill.encounters[0]).unwrap();
+        assert_eq!(67, comparison.duration);
+        assert_eq!(Some(65), comparison.best);
+        assert_eq!(Some(2), comparison.delta());
+        assert!(!comparison.is_best());
+        assert_eq!(Some(70), comparison.squad_best);
+        assert_eq!(Some(-3), comparison.squad_delta());
+        assert!(comparison.is_squad_best());
+        assert_eq!(Some(20 + 40), comparison.sum_of_best);
+
+        let reference = comparison.reference.unwrap();
+        assert_eq!((false, "b"), (reference.pinned, reference.file.as_str()));
+        let deltas: Vec<Option<i64>> = reference.splits.iter().map(Split::delta).collect();
+        assert_eq!(vec![Some(5), Some(-3)], deltas);
+
+        // failed pulls aren't compared
+        let wipe = record("f", 60, &["x", "y"], false, &[5]);
+        assert!(history.compare(&wipe, &wipe.encounters[0]).is_none());
+    }
+
+    #[tokio::test]
+    async fn compares_against_pinned_kills() {
+        let history = history("compares-pinned").await;
+        insert(&history, &record("a", 10, &["x"], true, &[30, 40]));
+        let later = record("c", 30, &["x"], true, &[50, 50]);
+        history.pin(
+            &Log::new(Target::Skor, format!("{}.zevtc", later.file)),
+            later,
+        );
+
+        let kill = record("b", 20, &["x"], true, &[45, 45]);
+        let comparison = history.compare(&kill, &kill.encounters[0]).unwrap();
+        assert_eq!(Some(70), comparison.best);
+        let reference = comparison.reference.unwrap();
+        assert_eq!((true, "c"), (reference.pinned, reference.file.as_str()));
+        let deltas: Vec<Option<i64>> = reference.splits.iter().map(Split::delta).collect();
+        assert_eq!(vec![Some(-5), Some(-5)], deltas);
+
+        assert!(history.unpin(Target::Skor));
+        assert!(!history.unpin(Target::Skor));
+        let comparison = history.compare(&kill, &kill.encounters[0]).unwrap();
+        assert_eq!(
+            Some("a"),
+            comparison.reference.as_ref().map(|r| r.file.as_str())
+        );
+    }
+
+    fn kill(phases: &[Time]) -> EncounterRecord {
+        record("", 0, &[], true, phases).encounters.remove(0)
+    }
+
+    #[test]
+    fn finds_best_splits() {
+        let kills = [
+            kill(&[30, 40, 50]),
+            kill(&[35, 20, 55]),
+            kill(&[40, 45, 25]),
+        ];
+        assert_eq!(vec![30, 20, 25], best_splits(kills.iter(), 3));
+        assert_eq!(vec![30, 40, 50], best_splits(kills[..1].iter(), 3));
+        assert!(best_splits(kills.iter(), 0).is_empty());
+        assert!(best_splits(std::iter::empty(), 3).is_empty());
+    }
+
+    #[test]
+    fn leaves_out_kills_missing_a_phase() {
+        // the second phase got skipped, so the rest don't line up
+        let kills = [kill(&[30, 40, 50]), kill(&[10, 10]), kill(&[35, 35, 55])];
+        assert_eq!(vec![30, 35, 50], best_splits(kills.iter(), 3));
+        assert_eq!(vec![10, 10], best_splits(kills.iter(), 2));
+        // no kill has this many phases
+        assert!(best_splits(kills.iter(), 4).is_empty());
+
+        let records = [
+            record("a", 1, &[], true, &[30, 40, 50]),
+            record("b", 2, &[], true, &[10, 10]),
+        ];
+        let kills: Vec<_> = successful(&records).collect();
+        let best = best_of(kills.into_iter()).unwrap();
+        assert_eq!(("b", vec![10, 10]), (best.file.as_str(), best.splits));
+    }
+}
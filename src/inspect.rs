@@ -0,0 +1,202 @@
+use crate::{
+    log::Log,
+    message::{fmt_time2, fmt_time3},
+    parse::{Data, Encounter, Event, Metadata, Time, Trace},
+    target::Target,
+};
+
+use std::fmt::{self, Display};
+
+use serde::Serialize;
+
+// which events to include in an event dump, nothing set means everything
+#[derive(Debug, Default, Clone)]
+pub struct EventFilter {
+    pub kinds: Vec<String>,
+    pub grep: Option<String>,
+}
+
+impl EventFilter {
+    fn matches(&self, event: &Event, text: &str) -> bool {
+        let kind_matches = self.kinds.is_empty()
+            || self
+                .kinds
+                .iter()
+                .any(|kind| kind.eq_ignore_ascii_case(event.kind.name()));
+        let text_matches = self
+            .grep
+            .as_ref()
+            .is_none_or(|grep| text.to_lowercase().contains(&grep.to_lowercase()));
+        kind_matches && text_matches
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Inspection {
+    file: String,
+    encounters: Vec<EncounterDetails>,
+    players: Vec<String>,
+    meta: Metadata,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<Trace>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    events: Option<Vec<EventDetails>>,
+}
+
+#[derive(Debug, Serialize)]
+struct EncounterDetails {
+    target: Target,
+    name: String,
+    success: bool,
+    phases: Vec<PhaseDetails>,
+}
+
+#[derive(Debug, Serialize)]
+struct PhaseDetails {
+    name: Option<String>,
+    start: Time,
+    end: Time,
+    duration: Time,
+}
+
+#[derive(Debug, Serialize)]
+struct EventDetails {
+    #[serde(flatten)]
+    event: Event,
+    text: String,
+}
+
+impl Inspection {
+    pub fn new(
+        log: &Log,
+        encounters: &[Encounter],
+        data: &Data,
+        trace: Option<Trace>,
+        filter: Option<&EventFilter>,
+    ) -> Self {
+        let encounters = encounters
+            .iter()
+            .map(|encounter| EncounterDetails {
+                target: encounter.target,
+                name: encounter.target.to_string(),
+                success: encounter.success,
+                phases: encounter
+                    .phases
+                    .iter()
+                    .map(|phase| PhaseDetails {
+                        name: phase.name.clone(),
+                        start: phase.start(),
+                        end: phase.end(),
+                        duration: phase.duration(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let mut players: Vec<String> = data.players.values().map(|p| p.name.clone()).collect();
+        players.sort();
+
+        let events = filter.map(|filter| {
+            data.events
+                .iter()
+                .filter_map(|event| {
+                    let text = event.pretty(&data.agents, &data.skills);
+                    if filter.matches(event, &text) {
+                        Some(EventDetails {
+                            event: event.clone(),
+                            text,
+                        })
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        });
+
+        Self {
+            file: log.file_name(),
+            encounters,
+            players,
+            meta: data.meta.clone(),
+            trace,
+            events,
+        }
+    }
+}
+
+impl Display for Inspection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.file)?;
+
+        for encounter in &self.encounters {
+            let success = if encounter.success {
+                "Success"
+            } else {
+                "Defeat"
+            };
+            let duration = encounter.phases.first().map_or(0, |p| p.duration);
+            writeln!(f)?;
+            writeln!(
+                f,
+                "{}: {} in {}",
+                encounter.name,
+                success,
+                fmt_time3(duration)
+            )?;
+
+            // first phase is the whole encounter
+            for (idx, phase) in encounter.phases.iter().enumerate() {
+                let name = match (&phase.name, idx) {
+                    (Some(name), _) => name.clone(),
+                    (None, 0) => "full".to_string(),
+                    (None, n) => n.to_string(),
+                };
+                writeln!(
+                    f,
+                    "    {:<10} {:>8} - {:>8} ({})",
+                    name,
+                    fmt_time2(phase.start),
+                    fmt_time2(phase.end),
+                    fmt_time3(phase.duration)
+                )?;
+            }
+        }
+
+        writeln!(f)?;
+        writeln!(f, "players: {}", self.players.join(", "))?;
+        writeln!(f, "arcdps build: {}", self.meta.arc_build)?;
+        if let Some(build) = self.meta.gw2_build {
+            writeln!(f, "gw2 build: {}", build)?;
+        }
+        if let Some(map_id) = self.meta.map_id {
+            writeln!(f, "map id: {}", map_id)?;
+        }
+        if let Some(pov) = &self.meta.pov {
+            writeln!(f, "recorded by: {}", pov)?;
+        }
+        if let Some(start) = self.meta.log_start {
+            writeln!(f, "log start: {}", start)?;
+        }
+        if let Some(end) = self.meta.log_end {
+            writeln!(f, "log end: {}", end)?;
+        }
+
+        if let Some(trace) = &self.trace {
+            writeln!(f)?;
+            writeln!(f, "trace:")?;
+            for note in trace.notes() {
+                writeln!(f, "    {:?}: {}", note.topic, note.message)?;
+            }
+        }
+
+        if let Some(events) = &self.events {
+            writeln!(f)?;
+            writeln!(f, "events ({}):", events.len())?;
+            for event in events {
+                writeln!(f, "{}", event.text)?;
+            }
+        }
+
+        Ok(())
+    }
+}
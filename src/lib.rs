@@ -1,12 +1,24 @@
 pub mod cache;
+pub mod chart;
+pub mod config;
 pub mod core;
 pub mod error;
 pub mod filter;
+pub mod history;
+pub mod inspect;
+pub mod live;
 pub mod log;
 pub mod message;
 pub mod parse;
+pub mod queue;
+pub mod report;
+pub mod retry;
 pub mod sender;
+pub mod session;
+pub mod sink;
+pub mod spec;
 pub mod target;
+pub mod template;
 pub mod upload;
 pub mod watcher;
 
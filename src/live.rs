@@ -0,0 +1,95 @@
+use crate::{cache::Cache, error::Result, session::Pull};
+
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PATH: &str = "live.json";
+
+// the message a session is being kept up to date in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub id: String,
+    // unix timestamp in milliseconds of the first pull of the session
+    pub session: i64,
+}
+
+// every pull since a daily reset, a unix timestamp in seconds
+#[derive(Debug, Default)]
+struct Pulls {
+    reset: Option<i64>,
+    pulls: Vec<Pull>,
+}
+
+// edits a single message per session as logs come in instead of posting one
+// per log, remembering which message that is across restarts
+#[derive(Debug, Clone)]
+pub struct Live {
+    cache: Arc<Mutex<Cache<String, Message>>>,
+    // of the reset the latest log was in
+    pulls: Arc<Mutex<Pulls>>,
+    // what the messages are sent to, each gets its own
+    key: String,
+    // minutes between pulls that start a new session
+    pub gap: u64,
+}
+
+impl Live {
+    pub async fn new(path: impl AsRef<Path>, key: &str, gap: u64) -> Result<Self> {
+        let cache = Arc::new(Mutex::new(Cache::new(path).await?));
+        Ok(Self {
+            cache,
+            pulls: Arc::new(Mutex::new(Pulls::default())),
+            key: key.to_string(),
+            gap,
+        })
+    }
+
+    // the id of the message of the session that started at `session`, if
+    // one has been sent already
+    pub fn message(&self, session: i64) -> Option<String> {
+        let cache = self.cache.lock().unwrap();
+        cache
+            .get(&self.key)
+            .filter(|message| message.session == session)
+            .map(|message| message.id.clone())
+    }
+
+    // adds `pull` to the pulls since `reset`, which only get read from disk
+    // with `load` when there aren't any yet, returning all of them
+    pub fn add_pull(
+        &self,
+        reset: i64,
+        pull: Pull,
+        load: impl FnOnce() -> Result<Vec<Pull>>,
+    ) -> Result<Vec<Pull>> {
+        let mut since = self.pulls.lock().unwrap();
+        if since.reset == Some(reset) {
+            // same as what's read from disk
+            if pull.target().fractal().is_some()
+                && !since
+                    .pulls
+                    .iter()
+                    .any(|other| other.log.path() == pull.log.path())
+            {
+                since.pulls.push(pull);
+            }
+        } else {
+            // the log that just came in is on disk as well
+            since.pulls = load()?;
+            since.reset = Some(reset);
+        }
+        Ok(since.pulls.clone())
+    }
+
+    pub fn set(&self, session: i64, id: String) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.insert(self.key.clone(), Message { id, session });
+        if let Err(e) = cache.save() {
+            log::error!("failed to save live message: {}", e);
+        }
+    }
+}
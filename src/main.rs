@@ -1,190 +1,959 @@
 use snek::{
     cache::{Cache, Log},
-    core, get_log_dir,
-    sender::Webhook,
+    config::{self, Config},
+    core::{self, Backfill, Options, Window},
+    error::{Error, Result},
+    filter::Only,
+    get_log_dir,
+    history::{self, History},
+    inspect::EventFilter,
+    live::{self, Live},
+    message::{self, AllowedMentions, Format, Mention, Mentions, Style},
+    queue::{self, Queue},
+    sender::{self, Destination, Fanout, Hook, Sender, Webhook},
+    sink::{self, Sink},
+    target::Target,
+    upload::Kind,
 };
 
-#[derive(Debug)]
-pub enum Mode {
-    Watch(Webhook),
-    Daily(Webhook),
-    Links,
-}
-
-#[tokio::main]
-async fn main() {
-    flexi_logger::Logger::with_str("info, snek = trace")
-        .format(flexi_logger::default_format)
-        .start()
-        .unwrap();
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+};
 
-    let mode = parse_args();
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use structopt::{
+    clap::{AppSettings, ErrorKind},
+    StructOpt,
+};
 
-    let log_dir = match get_log_dir() {
-        Ok(log_dir) => log_dir,
-        Err(_) => {
-            log::error!("log directory not found: as a backup option, make a file called `logdir.txt` in the same directory as snek and enter the path of your log directory in it i.e. `C:\\Users\\foobar\\Documents\\Guild Wars 2\\addons\\arcdps\\arcdps.cbtlogs`");
-            std::process::exit(1);
-        }
-    };
+mod exit {
+    pub const OK: i32 = 0;
+    // something went wrong at runtime, i.e. network or file errors
+    pub const FAILURE: i32 = 1;
+    // invalid command line usage
+    pub const USAGE: i32 = 2;
+    // problem with the config file or log directory
+    pub const CONFIG: i32 = 3;
+    // referenced webhook or log doesn't exist
+    pub const NOT_FOUND: i32 = 4;
+}
 
-    let cache = Log::new("log_cache").await.unwrap();
-    let url = "https://dps.report/";
+const AFTER_HELP: &str = "\
+For example, initial setup:
+> ./snek add my_webhook https://discord.com/api/webhooks/ABCDEFGHIJKLMNOPQR/ABCDEFGHIJKLMNOPQRSTUVWXYZ01234567890ABCDEFGHIJKLMNOPQRSTUVWXYZ01234
+> ./snek watch my_webhook
 
-    let result = match mode {
-        Mode::Daily(hook) => core::daily(log_dir, url, hook, cache).await,
-        Mode::Links => core::links(log_dir, url, std::io::stdout(), cache).await,
-        Mode::Watch(hook) => core::watch(log_dir, url, hook, cache).await,
-    };
+snek checks the default arcdps log directory and if it can't find anything
+then it looks for a file in the same directory as it called `logdir.txt` which
+is assumed to contain the user's actual arcdps log directory (for example,
+C:\\Users\\you\\Documents\\Guild Wars 2\\addons\\arcdps\\arcdps.cbtlogs). Either
+can be overridden with `--log-dir` or `log_dir` in the config file.";
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "snek",
+    about = "Automatic GW2 log uploader for fractal CMs",
+    after_help = AFTER_HELP,
+    global_settings = &[AppSettings::VersionlessSubcommands],
+)]
+struct Opt {
+    /// Path of the arcdps log directory
+    #[structopt(long, global = true, parse(from_os_str))]
+    log_dir: Option<PathBuf>,
+
+    /// Path of the config file [default: snek.toml]
+    #[structopt(long, global = true, parse(from_os_str))]
+    config: Option<PathBuf>,
+
+    /// Don't upload or post anything, print what would be posted instead
+    #[structopt(long, global = true)]
+    dry_run: bool,
+
+    /// Output JSON instead of plaintext
+    #[structopt(long, global = true)]
+    json: bool,
+
+    /// Output more detailed logging
+    #[structopt(short, long, global = true)]
+    verbose: bool,
+
+    #[structopt(subcommand)]
+    cmd: Command,
+}
 
-    match result {
-        Ok(()) => {}
-        Err(e) => {
-            log::error!("error: {}", e);
-        }
-    }
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Watch for incoming fractal CM logs, upload & post to each webhook
+    /// <names> in the style & with the filter it was added with
+    Watch {
+        names: Vec<String>,
+
+        /// Keep a single summary of tonight's run up to date instead of
+        /// posting every log
+        #[structopt(long)]
+        live: bool,
+
+        /// Minutes between pulls that start a new session when live [default:
+        /// 60]
+        #[structopt(long)]
+        gap: Option<u64>,
+    },
+
+    /// Upload & post set of most recent fractal CM logs to each webhook
+    /// <names>
+    Daily {
+        names: Vec<String>,
+
+        /// How far back to look for kills: reset, all or i.e. 12h [default:
+        /// reset]
+        #[structopt(long)]
+        window: Option<Window>,
+    },
+
+    /// Upload & post a summary of the most recent session, including full
+    /// clear times of each fractal, to webhook <name>
+    Session {
+        name: Option<String>,
+
+        /// How far back to look for the session: reset, all or i.e. 12h
+        /// [default: reset]
+        #[structopt(long)]
+        window: Option<Window>,
+
+        /// Minutes between pulls that start a new session [default: 60]
+        #[structopt(long)]
+        gap: Option<u64>,
+    },
+
+    /// Summarise kills, wipes & deaths recorded over a period of time & post
+    /// to webhook <name>, or output as plaintext if no webhook is given
+    Report {
+        name: Option<String>,
+
+        /// First day of the report, i.e. 2020-09-01 [default: <days> before
+        /// <until>]
+        #[structopt(long)]
+        since: Option<NaiveDate>,
+
+        /// Last day of the report, i.e. 2020-09-07 [default: <days> after
+        /// <since>, or today]
+        #[structopt(long)]
+        until: Option<NaiveDate>,
+
+        /// Number of days the report covers if either end isn't given
+        #[structopt(long, default_value = "7")]
+        days: u32,
+    },
+
+    /// List the fastest recorded kill & best phase splits of each target
+    Records {
+        /// List records of each squad instead of overall records
+        #[structopt(long)]
+        squad: bool,
+    },
+
+    /// Compare kills of the target of <file> against it instead of the
+    /// fastest recorded kill
+    Pin {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+
+    /// Go back to comparing kills of <target> against the fastest recorded
+    /// kill
+    Unpin { target: Target },
+
+    /// Upload set of recent fractal CM logs & output as plaintext or JSON
+    Links {
+        /// How far back to look for kills: reset, all or i.e. 12h [default:
+        /// reset]
+        #[structopt(long)]
+        window: Option<Window>,
+    },
+
+    /// Upload every fractal CM log in the log directory that hasn't been
+    /// uploaded yet & record it in the history
+    Backfill {
+        /// Only upload logs from this date onwards, i.e. 2020-09-01
+        #[structopt(long)]
+        since: Option<NaiveDate>,
+
+        /// Only upload logs of these targets, i.e. skor,arts,arkk
+        #[structopt(long, use_delimiter = true)]
+        targets: Vec<Target>,
+
+        /// Maximum number of logs to upload at once
+        #[structopt(long, default_value = "4")]
+        jobs: usize,
+    },
+
+    /// Parse logs without uploading & output their details
+    Parse {
+        #[structopt(required = true, parse(from_os_str))]
+        files: Vec<PathBuf>,
+    },
+
+    /// Parse a single log without uploading & output everything known about
+    /// it, optionally including a dump of its events
+    Inspect {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+
+        /// Include the reasoning behind success and phase decisions
+        #[structopt(long)]
+        trace: bool,
+
+        /// Include events in the output
+        #[structopt(long)]
+        events: bool,
+
+        /// Only include events of this kind, i.e. BuffApply (implies --events)
+        #[structopt(long = "kind")]
+        kinds: Vec<String>,
+
+        /// Only include events containing this text (implies --events)
+        #[structopt(long)]
+        grep: Option<String>,
+    },
+
+    /// Show the dps.report user token logs are uploaded under, or save
+    /// <token> to the config file to group future uploads under it
+    Token { token: Option<String> },
+
+    /// Add a webhook: <name> is used to reference the webhook in other
+    /// commands, <url> is a Discord or Slack webhook url, a Matrix homeserver,
+    /// anywhere else that takes a JSON POST or a file to keep a record of logs
+    /// in
+    Add {
+        name: String,
+        url: String,
+
+        /// What <url> is: discord, slack, matrix, json, or a jsonl, csv,
+        /// markdown or html file [default: discord]
+        #[structopt(long)]
+        kind: Option<sender::Kind>,
+
+        /// Room id to post in, for Matrix, i.e. !abcdefg:matrix.org
+        #[structopt(long, required_if("kind", "matrix"))]
+        room: Option<String>,
+
+        /// Access token of the user to post as, for Matrix
+        #[structopt(long, required_if("kind", "matrix"))]
+        access_token: Option<String>,
+
+        /// Body to post, for JSON: {text} is replaced by the message as text
+        /// & {message} by the message as JSON, i.e. '{"body": "{text}"}'
+        /// [default: the message as JSON]
+        #[structopt(long)]
+        template: Option<String>,
+
+        /// Upload logs posted to this webhook with: dps-report, wingman, local
+        /// or offline [default: `uploader` in the config file]
+        #[structopt(long)]
+        uploader: Option<Kind>,
+
+        /// Post in the thread or forum post with this id
+        #[structopt(long)]
+        thread_id: Option<String>,
+
+        /// Start a forum post with this name for every message
+        #[structopt(long, conflicts_with = "thread-id")]
+        thread_name: Option<String>,
+
+        /// Mention a role or user when a kill sets a new record: role:<id>,
+        /// user:<id>, <@&id> or <@id>
+        #[structopt(long, number_of_values = 1)]
+        mention_record: Vec<Mention>,
+
+        /// Mention a role or user when a pull is a wipe: role:<id>,
+        /// user:<id>, <@&id> or <@id>
+        #[structopt(long, number_of_values = 1)]
+        mention_wipe: Vec<Mention>,
+
+        /// Show mentions without pinging anyone
+        #[structopt(long)]
+        no_ping: bool,
+
+        /// Which logs `watch` & `daily` post to this webhook: all, kills,
+        /// records or wipes [default: all]
+        #[structopt(long)]
+        only: Option<Only>,
+
+        /// How messages to this webhook look: embed or text [default: embed]
+        #[structopt(long)]
+        style: Option<Style>,
+    },
+
+    /// Forget the webhook <name>
+    Remove { name: String },
+
+    /// List known webhooks
+    List,
+
+    /// List, retry or drop uploads & messages that failed and are waiting to
+    /// be tried again by `watch`
+    Queue(QueueCommand),
+
+    /// Version, background information and whatever
+    About,
 }
 
-fn parse_args() -> Mode {
-    use std::env;
+#[derive(Debug, StructOpt)]
+enum QueueCommand {
+    /// List queued uploads & messages, oldest first
+    List,
 
-    let mut args = env::args();
-    args.next().unwrap();
+    /// Have `watch` try queued items with one of <ids> right away, even if
+    /// they failed for good, or every item if none are given
+    Retry { ids: Vec<String> },
 
-    let mode_name = args.next().unwrap_or_else(|| usage());
+    /// Forget queued items with one of <ids>, i.e. 20200407-174541
+    Drop {
+        #[structopt(required_unless = "all")]
+        ids: Vec<String>,
 
-    match mode_name.as_ref() {
-        "daily" => {
-            let hook = parse_webhook_args(&mut args);
-            return Mode::Daily(hook);
+        /// Forget every queued item
+        #[structopt(long, conflicts_with = "ids")]
+        all: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    let opt = match Opt::from_iter_safe(std::env::args_os()) {
+        Ok(opt) => opt,
+        Err(e)
+            if matches!(
+                e.kind,
+                ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed
+            ) =>
+        {
+            println!("{}", e.message);
+            std::process::exit(exit::OK);
         }
-        "links" => return Mode::Links,
-        "watch" => {
-            let hook = parse_webhook_args(&mut args);
-            return Mode::Watch(hook);
+        Err(e) => {
+            eprintln!("{}", e.message);
+            std::process::exit(exit::USAGE);
         }
+    };
 
-        "add" => add_webhook(&mut args),
-        "remove" => remove_webhook(&mut args),
-        "list" => list_webhooks(),
+    // per-event parser output is only shown when asked for
+    let log_spec = if opt.verbose {
+        "info, snek = trace"
+    } else {
+        "info, snek = trace, snek::parse = debug"
+    };
+    flexi_logger::Logger::with_env_or_str(log_spec)
+        .format(flexi_logger::default_format)
+        .start()
+        .unwrap();
 
-        "about" => about(),
+    let config = match &opt.config {
+        Some(path) => Config::load(path),
+        None => Config::load_or_default(config::DEFAULT_PATH),
+    };
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            log::error!("failed to load config: {}", e);
+            std::process::exit(exit::CONFIG);
+        }
+    };
 
-        _ => {
-            eprintln!("invalid mode: try `./snek` for usage");
-            std::process::exit(1);
+    let code = match run(opt, config).await {
+        Ok(()) => exit::OK,
+        Err(e) => {
+            log::error!("error: {}", e);
+            exit_code(&e)
         }
     };
 
-    std::process::exit(0);
+    std::process::exit(code);
 }
 
-fn usage() -> ! {
-    println!("\
-usage:
-    ./snek watch <name>
-        Watch for incoming fractal CM logs, upload & post to webhook <name> as
-        embed
+fn exit_code(e: &Error) -> i32 {
+    match e {
+        Error::LogDirectory | Error::Toml(_) | Error::TomlEdit(_) => exit::CONFIG,
+        Error::UnknownWebhook(_)
+        | Error::UnknownQueueItem
+        | Error::NoRecentLog
+        | Error::UnsupportedLog(_)
+        | Error::MissingReport(_) => exit::NOT_FOUND,
+        Error::InvalidWebhookUrl(_) | Error::InvalidWebhook(_) => exit::USAGE,
+        _ => exit::FAILURE,
+    }
+}
 
-    ./snek daily <name>
-        Upload & post set of most recent fractal CM logs to webhook <name>
+async fn run(opt: Opt, config: Config) -> Result<()> {
+    let Opt {
+        log_dir,
+        config: config_path,
+        dry_run,
+        json,
+        cmd,
+        ..
+    } = opt;
+
+    let format = if json { Format::Json } else { Format::Text };
+    let options = Options::new(&config.upload_url)
+        .dry_run(dry_run)
+        .uploader(config.uploader(config.uploader)?)
+        .retry(config.retry)
+        .max_uploads(config.max_uploads)
+        .failover(config.failover)
+        .attach_logs(config.attach_logs)
+        .templates(config.templates.clone())
+        .charts(config.charts.clone());
+
+    match cmd {
+        Command::Watch { names, live, gap } => {
+            let options = with_stores(options).await?;
+            let log_dir = find_log_dir(log_dir, &config)?;
+            let entries = entries(&names, dry_run)?;
+            let mut options = options.uploader(config.uploader(uploader(&entries, &config)?)?);
+            if live && !dry_run {
+                // the whole session would be posted again for every log
+                if let Some((name, _)) = entries
+                    .iter()
+                    .find(|(_, entry)| !entry.kind.can_edit() && !entry.kind.is_file())
+                {
+                    return Err(Error::InvalidWebhook(format!(
+                        "`{}` can't edit messages, which --live needs",
+                        name
+                    )));
+                }
+            }
+            if live {
+                let gap = gap.unwrap_or(config.session_gap);
+                let key = names.join(",");
+                options = options.live(Live::new(live::DEFAULT_PATH, &key, gap).await?);
+            }
+            let cache = Log::new("log_cache").await?;
+            if dry_run {
+                let stdout = fanout(entries, &config, |_| Ok(sender::Write::new(io::stdout())))?;
+                core::watch(log_dir, &options, stdout, cache).await
+            } else {
+                let hooks = fanout(entries, &config, |entry| hook(entry, &config))?;
+                core::watch(log_dir, &options, hooks, cache).await
+            }
+        }
+        Command::Daily { names, window } => {
+            let options = with_stores(options).await?;
+            let options = options.window(window.unwrap_or(config.daily_window));
+            let log_dir = find_log_dir(log_dir, &config)?;
+            let entries = entries(&names, dry_run)?;
+            let options = options.uploader(config.uploader(uploader(&entries, &config)?)?);
+            let cache = Log::new("log_cache").await?;
+            if dry_run {
+                let stdout = fanout(entries, &config, |_| Ok(sender::Write::new(io::stdout())))?;
+                core::daily(log_dir, &options, stdout, cache).await
+            } else {
+                let hooks = fanout(entries, &config, |entry| hook(entry, &config))?;
+                core::daily(log_dir, &options, hooks, cache).await
+            }
+        }
+        Command::Session { name, window, gap } => {
+            let options = with_stores(options).await?;
+            let options = options.window(window.unwrap_or(config.daily_window));
+            let gap = gap.unwrap_or(config.session_gap);
+            let log_dir = find_log_dir(log_dir, &config)?;
+            let (_, entry) = entries(name.as_slice(), dry_run)?.remove(0);
+            let options = options
+                .uploader(config.uploader(entry.uploader.unwrap_or(config.uploader))?)
+                .mentions(entry.mentions.clone());
+            let cache = Log::new("log_cache").await?;
+            if dry_run {
+                let stdout = sender::Write::new(io::stdout());
+                core::session(log_dir, &options, stdout, cache, gap).await
+            } else {
+                let hook = hook(&entry, &config)?;
+                core::session(log_dir, &options, hook, cache, gap).await
+            }
+        }
+        Command::Report {
+            name,
+            since,
+            until,
+            days,
+        } => {
+            let options = options.history(History::new(history::DEFAULT_PATH).await?);
+            let log_dir = find_log_dir(log_dir, &config)?;
+            // dates are as of daily reset, which is 00:00 UTC
+            let length = chrono::Duration::days(days.max(1) as i64 - 1);
+            let (since, until) = match (since, until) {
+                (Some(since), Some(until)) => (since, until),
+                (Some(since), None) => (since, since + length),
+                (None, Some(until)) => (until - length, until),
+                (None, None) => {
+                    let today = chrono::Utc::now().naive_utc().date();
+                    (today - length, today)
+                }
+            };
+            let stdout = sender::Write::new(io::stdout());
+            match name {
+                Some(name) => {
+                    let msg_gen = message::WebhookGenerator::default();
+                    if dry_run {
+                        core::report(log_dir, &options, since, until, stdout, msg_gen).await
+                    } else {
+                        let hook = hook(&entry(&name)?, &config)?;
+                        core::report(log_dir, &options, since, until, hook, msg_gen).await
+                    }
+                }
+                None => {
+                    let msg_gen = message::TextGenerator::default();
+                    core::report(log_dir, &options, since, until, stdout, msg_gen).await
+                }
+            }
+        }
+        Command::Records { squad } => {
+            let options = options.history(History::new(history::DEFAULT_PATH).await?);
+            core::records(&options, io::stdout(), format, squad)
+        }
+        Command::Pin { file } => {
+            let options = options.history(History::new(history::DEFAULT_PATH).await?);
+            core::pin(&options, &file, io::stdout())
+        }
+        Command::Unpin { target } => {
+            let options = options.history(History::new(history::DEFAULT_PATH).await?);
+            core::unpin(&options, target, io::stdout())
+        }
+        Command::Links { window } => {
+            let options = with_stores(options).await?;
+            let options = options.window(window.unwrap_or(config.daily_window));
+            let log_dir = find_log_dir(log_dir, &config)?;
+            let cache = Log::new("log_cache").await?;
+            core::links(log_dir, &options, io::stdout(), cache, format).await
+        }
+        Command::Backfill {
+            since,
+            targets,
+            jobs,
+        } => {
+            let options = with_stores(options).await?;
+            let log_dir = find_log_dir(log_dir, &config)?;
+            let cache = Log::new("log_cache").await?;
+            let backfill = Backfill {
+                since,
+                targets,
+                jobs,
+            };
+            core::backfill(log_dir, &options, &backfill, io::stdout(), cache, format).await
+        }
+        Command::Parse { files } => core::parse(&files, io::stdout(), format).await,
+        Command::Inspect {
+            file,
+            trace,
+            events,
+            kinds,
+            grep,
+        } => {
+            let filter = if events || !kinds.is_empty() || grep.is_some() {
+                Some(EventFilter { kinds, grep })
+            } else {
+                None
+            };
+            core::inspect(&file, io::stdout(), format, filter.as_ref(), trace)
+        }
+        Command::Token { token: Some(token) } => {
+            let path = config_path.unwrap_or_else(|| config::DEFAULT_PATH.into());
+            config::save_token(path, &token)
+        }
+        Command::Token { token: None } => {
+            match &config.upload.user_token {
+                Some(token) => println!("{}", token),
+                None => println!("no user token set"),
+            }
+            Ok(())
+        }
+        Command::Add {
+            name,
+            url,
+            kind,
+            room,
+            access_token,
+            template,
+            uploader,
+            thread_id,
+            thread_name,
+            mention_record,
+            mention_wipe,
+            no_ping,
+            only,
+            style,
+        } => {
+            let entry = Entry {
+                url,
+                kind: kind.unwrap_or_default(),
+                room,
+                access_token,
+                template,
+                uploader,
+                thread_id,
+                thread_name,
+                mentions: Mentions {
+                    records: mention_record,
+                    wipes: mention_wipe,
+                },
+                allowed_mentions: if no_ping {
+                    Some(AllowedMentions::default())
+                } else {
+                    None
+                },
+                only: only.unwrap_or_default(),
+                style: style.unwrap_or_default(),
+            };
+            add_webhook(name, entry)
+        }
+        Command::Remove { name } => remove_webhook(&name),
+        Command::List => list_webhooks(format),
+        Command::Queue(cmd) => {
+            let queue = Queue::new(queue::DEFAULT_PATH).await?;
+            manage_queue(&queue, cmd, format)
+        }
+        Command::About => {
+            about();
+            Ok(())
+        }
+    }
+}
 
-    ./snek links
-        Upload set of recent fractal CM logs & output as plaintext
+// what's been uploaded & what's waiting to be, only needed by commands that
+// upload logs
+async fn with_stores(options: Options) -> Result<Options> {
+    Ok(options
+        .history(History::new(history::DEFAULT_PATH).await?)
+        .queue(Queue::new(queue::DEFAULT_PATH).await?))
+}
 
-    ./snek add <name> <url>
-        Add a webhook: <name> is used to reference the webhook in other
-        commands, <url> is a Discord webhook url
+fn find_log_dir(arg: Option<PathBuf>, config: &Config) -> Result<PathBuf> {
+    let log_dir = match arg.or_else(|| config.log_dir.clone()) {
+        Some(log_dir) => log_dir,
+        None => get_log_dir().inspect_err(|_| {
+            log::error!("log directory not found: as a backup option, make a file called `logdir.txt` in the same directory as snek and enter the path of your log directory in it i.e. `C:\\Users\\foobar\\Documents\\Guild Wars 2\\addons\\arcdps\\arcdps.cbtlogs`");
+        })?,
+    };
 
-    ./snek remove <name>
-        Forget the webhook <name>
+    if !log_dir.is_dir() {
+        log::error!("`{}` is not a directory", log_dir.display());
+        return Err(Error::LogDirectory);
+    }
 
-    ./snek list
-        List known webhooks
+    Ok(log_dir)
+}
 
-    ./snek about
-        Version, background information and whatever
+const WEBHOOKS: &str = "webhooks.json";
+// webhooks used to be stored as bincode, name to url
+const LEGACY_WEBHOOKS: &str = "webhooks";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    #[serde(default, skip_serializing_if = "sender::Kind::is_discord")]
+    kind: sender::Kind,
+    // where & as whom matrix messages go
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    room: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    access_token: Option<String>,
+    // what's posted to json webhooks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    template: Option<String>,
+    // overrides `uploader` in the config file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uploader: Option<Kind>,
+    // a thread or forum post to post in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thread_id: Option<String>,
+    // starts a forum post named this for every message instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thread_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Mentions::is_empty")]
+    mentions: Mentions,
+    // who may be pinged, only those in `mentions` if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    allowed_mentions: Option<AllowedMentions>,
+    // which logs `watch` & `daily` post here
+    #[serde(default, skip_serializing_if = "Only::is_all")]
+    only: Only,
+    #[serde(default, skip_serializing_if = "Style::is_embed")]
+    style: Style,
+}
 
-For example, initial setup:
-> ./snek add my_webhook https://discordapp.com/api/webhooks/ABCDEFGHIJKLMNOPQR/ABCDEFGHIJKLMNOPQRSTUVWXYZ01234567890ABCDEFGHIJKLMNOPQRSTUVWXYZ01234
-> ./snek watch my_webhook
+fn webhook_store() -> Result<Cache<String, Entry>> {
+    let mut webhooks = Cache::new_blocking(WEBHOOKS)?;
+    if !Path::new(WEBHOOKS).exists() && Path::new(LEGACY_WEBHOOKS).is_file() {
+        let legacy: Cache<String, String> = Cache::new_blocking(LEGACY_WEBHOOKS)?;
+        for (name, url) in legacy.raw() {
+            let entry = Entry {
+                url: url.clone(),
+                ..Entry::default()
+            };
+            webhooks.insert(name.clone(), entry);
+        }
+        log::info!("moved webhooks over to `{}`", WEBHOOKS);
+    }
+    Ok(webhooks)
+}
 
-snek checks the default arcdps log directory and if it can't find anything
-then it looks for a file in the same directory as it called `logdir.txt` which
-is assumed to contain the user's actual arcdps log directory (for example,
-C:\\Users\\you\\Documents\\Guild Wars 2\\addons\\arcdps\\arcdps.cbtlogs) ");
-    std::process::exit(0)
+fn entry(name: &str) -> Result<Entry> {
+    let webhooks = webhook_store()?;
+    webhooks
+        .get(&name.to_string())
+        .cloned()
+        .ok_or_else(|| Error::UnknownWebhook(name.to_string()))
 }
 
-fn parse_webhook_args(args: &mut std::env::Args) -> Webhook {
-    let webhooks: Cache<String, String> =
-        Cache::new_blocking("webhooks").expect("failed to read webhook store");
+// the webhooks named `names`, which don't have to exist if nothing's actually
+// posted, nor be given at all
+fn entries(names: &[String], dry_run: bool) -> Result<Vec<(String, Entry)>> {
+    if !dry_run {
+        if names.is_empty() {
+            return Err(Error::InvalidWebhook(
+                "name at least one to post to, or use --dry-run".to_string(),
+            ));
+        }
+        return names
+            .iter()
+            .map(|name| Ok((name.clone(), entry(name)?)))
+            .collect();
+    }
 
-    let hook_name = args.next().unwrap_or_else(|| {
-        eprintln!("invalid arguments: missing webhook name");
-        std::process::exit(1);
-    });
+    let webhooks = webhook_store()?;
+    if names.is_empty() {
+        return Ok(vec![("stdout".to_string(), Entry::default())]);
+    }
+    Ok(names
+        .iter()
+        .map(|name| {
+            let entry = webhooks.get(name).cloned().unwrap_or_default();
+            (name.clone(), entry)
+        })
+        .collect())
+}
 
-    let hook_url = webhooks.get(&hook_name).unwrap_or_else(|| {
-        eprintln!("no such webhook: try adding with `./snek add <name> <url>`");
-        std::process::exit(1);
-    });
+fn hook(entry: &Entry, config: &Config) -> Result<Hook> {
+    let (client, retry) = (config.client()?, config.retry);
+    let hook = match entry.kind {
+        sender::Kind::Discord => {
+            let allowed_mentions = entry
+                .allowed_mentions
+                .clone()
+                .or_else(|| (!entry.mentions.is_empty()).then(|| entry.mentions.allowed()));
+            Hook::Discord(
+                Webhook::new(&entry.url)
+                    .with_client(client)
+                    .with_retry(retry)
+                    .with_thread_id(entry.thread_id.clone())
+                    .with_thread_name(entry.thread_name.clone())
+                    .with_allowed_mentions(allowed_mentions),
+            )
+        }
+        sender::Kind::Slack => Hook::Slack(
+            sender::Slack::new(&entry.url)
+                .with_client(client)
+                .with_retry(retry),
+        ),
+        sender::Kind::Matrix => Hook::Matrix(
+            sender::Matrix::new(
+                &entry.url,
+                entry.room.as_deref().unwrap_or_default(),
+                entry.access_token.as_deref().unwrap_or_default(),
+            )
+            .with_client(client)
+            .with_retry(retry),
+        ),
+        sender::Kind::Json => Hook::Json(
+            sender::Json::new(&entry.url, entry.template.clone())
+                .with_client(client)
+                .with_retry(retry),
+        ),
+        sender::Kind::Jsonl => Hook::Sink(Sink::new(&entry.url, sink::Format::Jsonl)),
+        sender::Kind::Csv => Hook::Sink(Sink::new(&entry.url, sink::Format::Csv)),
+        sender::Kind::Markdown => Hook::Sink(Sink::new(&entry.url, sink::Format::Markdown)),
+        sender::Kind::Html => Hook::Sink(Sink::new(&entry.url, sink::Format::Html)),
+    };
+    Ok(hook)
+}
 
-    Webhook::new(&hook_url)
+// the uploader `entries` override the config's with, logs only being
+// uploaded once for all of them
+fn uploader(entries: &[(String, Entry)], config: &Config) -> Result<Kind> {
+    let mut overrides = entries
+        .iter()
+        .filter_map(|(name, entry)| entry.uploader.map(|kind| (name, kind)));
+    let (first, kind) = match overrides.next() {
+        Some(o) => o,
+        None => return Ok(config.uploader),
+    };
+    match overrides.find(|(_, other)| *other != kind) {
+        Some((name, _)) => Err(Error::InvalidWebhook(format!(
+            "`{}` and `{}` override the uploader differently",
+            first, name
+        ))),
+        None => Ok(kind),
+    }
 }
 
-fn add_webhook(args: &mut std::env::Args) {
-    let mut webhooks: Cache<String, String> =
-        Cache::new_blocking("webhooks").expect("failed to read webhook store");
+// `entries` as destinations of a single message, `sender` making what
+// actually sends to each
+fn fanout<S, F>(entries: Vec<(String, Entry)>, config: &Config, sender: F) -> Result<Fanout<S>>
+where
+    S: Sender,
+    F: Fn(&Entry) -> Result<S>,
+{
+    let mut destinations = Vec::new();
+    for (name, entry) in entries {
+        destinations.push(Destination {
+            name,
+            sender: sender(&entry)?,
+            only: entry.only,
+            style: entry.style,
+            mentions: entry.mentions,
+            templates: config.templates.clone(),
+        });
+    }
+    Ok(Fanout::new(destinations))
+}
 
-    if let (Some(hook_name), Some(hook_url)) = (args.next(), args.next()) {
-        if !Webhook::validate_url(&hook_url) {
-            eprintln!("\
-error: ./snek add <name> <url>
-    provided url is incorrect format, should be copied directly from discord
-    i.e. ./snek add my_webhook https://discordapp.com/api/webhooks/ABCDEFGHIJKLMNOPQR/ABCDEFGHIJKLMNOPQRSTUVWXYZ01234567890ABCDEFGHIJKLMNOPQRSTUVWXYZ01234");
-            std::process::exit(1);
+fn add_webhook(name: String, entry: Entry) -> Result<()> {
+    if !entry.kind.validate_url(&entry.url) {
+        let example = match entry.kind {
+            sender::Kind::Discord => "should be copied directly from discord\n    i.e. ./snek add my_webhook https://discord.com/api/webhooks/ABCDEFGHIJKLMNOPQR/ABCDEFGHIJKLMNOPQRSTUVWXYZ01234567890ABCDEFGHIJKLMNOPQRSTUVWXYZ01234",
+            sender::Kind::Slack => "should be copied directly from slack\n    i.e. ./snek add my_webhook --kind slack https://hooks.slack.com/services/T0000/B0000/XXXXXXXX",
+            sender::Kind::Matrix => "should be the homeserver\n    i.e. ./snek add my_room --kind matrix https://matrix.org --room '!abcdefg:matrix.org' --access-token <token>",
+            sender::Kind::Json => "should be an http or https url",
+            _ => "should be the path of the file to write to",
+        };
+        eprintln!(
+            "error: ./snek add <name> <url>\n    provided url is incorrect format, {}",
+            example
+        );
+        return Err(Error::InvalidWebhookUrl(entry.url));
+    }
+    if entry.kind == sender::Kind::Matrix
+        && !entry
+            .room
+            .as_deref()
+            .is_some_and(sender::Matrix::validate_room)
+    {
+        return Err(Error::InvalidWebhook(
+            "matrix rooms are given by id, i.e. !abcdefg:matrix.org".to_string(),
+        ));
+    }
+    if let Some(template) = &entry.template {
+        if !sender::Json::validate_template(template) {
+            return Err(Error::InvalidWebhook(format!(
+                "template doesn't make valid JSON: {}",
+                template
+            )));
         }
+    }
 
-        webhooks.insert(hook_name.clone(), hook_url);
+    let mut webhooks = webhook_store()?;
+    webhooks.insert(name.clone(), entry);
 
-        log::info!("added webhook `{}`", hook_name);
-    } else {
-        eprintln!("invalid arguments: try `./snek add <name> <url>`");
-        std::process::exit(1);
-    }
+    log::info!("added webhook `{}`", name);
+    Ok(())
 }
 
-fn remove_webhook(args: &mut std::env::Args) {
-    let mut webhooks: Cache<String, String> =
-        Cache::new_blocking("webhooks").expect("failed to read webhook store");
+fn remove_webhook(name: &str) -> Result<()> {
+    let mut webhooks = webhook_store()?;
 
-    if let Some(hook_name) = args.next() {
-        match webhooks.remove(&hook_name) {
-            Some(_) => log::info!("removed webhook `{}`", &hook_name),
-            None => log::error!("`{}` is not a known webhook", &hook_name),
-        };
-    } else {
-        eprintln!("invalid arguments: try `./snek remove <name>`");
-        std::process::exit(1);
+    match webhooks.remove(&name.to_string()) {
+        Some(_) => {
+            log::info!("removed webhook `{}`", name);
+            Ok(())
+        }
+        None => Err(Error::UnknownWebhook(name.to_string())),
     }
 }
 
-fn list_webhooks() {
-    let webhooks: Cache<String, String> =
-        Cache::new_blocking("webhooks").expect("failed to read webhook store");
+fn list_webhooks(format: Format) -> Result<()> {
+    let webhooks = webhook_store()?;
+    let sorted: BTreeMap<_, _> = webhooks.raw().iter().collect();
+
+    if format == Format::Json {
+        println!("{}", serde_json::to_string_pretty(&sorted)?);
+        return Ok(());
+    }
 
     log::info!("listing known webhooks");
-    for (k, v) in webhooks.raw() {
-        println!("{}: {}", k, v);
+    for (name, entry) in &sorted {
+        match entry.uploader {
+            Some(uploader) => println!("{}: {} ({})", name, entry.url, uploader),
+            None => println!("{}: {}", name, entry.url),
+        }
+        if entry.kind.is_file() {
+            println!("    {} file", entry.kind);
+        } else if !entry.kind.is_discord() {
+            println!("    {} webhook", entry.kind);
+        }
+        if let Some(room) = &entry.room {
+            println!("    in room {}", room);
+        }
+        if !entry.only.is_all() || !entry.style.is_embed() {
+            println!("    {} as {}", entry.only, entry.style);
+        }
+        if let Some(thread_id) = &entry.thread_id {
+            println!("    in thread {}", thread_id);
+        }
+        if let Some(thread_name) = &entry.thread_name {
+            println!("    in new forum posts named `{}`", thread_name);
+        }
+        for (what, mentions) in &[
+            ("records", &entry.mentions.records),
+            ("wipes", &entry.mentions.wipes),
+        ] {
+            if !mentions.is_empty() {
+                let mentions: Vec<String> = mentions.iter().map(ToString::to_string).collect();
+                println!("    mentions {} on {}", mentions.join(" "), what);
+            }
+        }
     }
 
-    if webhooks.raw().is_empty() {
+    if sorted.is_empty() {
         println!("no known webhooks");
     }
+
+    Ok(())
 }
 
-fn about() -> ! {
+fn manage_queue(queue: &Queue, cmd: QueueCommand, format: Format) -> Result<()> {
+    let now = chrono::Utc::now().timestamp();
+    match cmd {
+        QueueCommand::List => {
+            let items = queue.items();
+            if format == Format::Json {
+                println!("{}", serde_json::to_string_pretty(&items)?);
+            } else if items.is_empty() {
+                println!("nothing queued");
+            }
+            for item in items.iter().filter(|_| format == Format::Text) {
+                println!("{}", item);
+            }
+        }
+        QueueCommand::Retry { ids } => {
+            let count = queue.retry(&ids, now);
+            log::info!("{} queued items will be retried", count);
+        }
+        QueueCommand::Drop { ids, all } => {
+            // no ids means everything
+            let ids = if all { Vec::new() } else { ids };
+            let count = queue.remove(&ids);
+            if count == 0 {
+                return Err(Error::UnknownQueueItem);
+            }
+            log::info!("dropped {} queued items", count);
+        }
+    }
+    Ok(())
+}
+
+fn about() {
     println!(
         "  v1.0.4 20201031
 
@@ -200,6 +969,4 @@ free to send me the .[z]evtc files for these logs and I'll fix the issue
           parsing errors
 "
     );
-
-    std::process::exit(0)
 }
@@ -1,9 +1,18 @@
-use crate::core::LogInfo;
+use crate::core::{LogInfo, UploadedLog};
+use crate::history::Comparison;
+use crate::log::Log;
 use crate::parse::Encounter;
+use crate::parse::Phase;
+use crate::report::Report;
+use crate::session::Session;
+use crate::target::Target;
+use crate::template::{EmbedField, Squad, Templates};
 
+use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Display;
 
+use chrono::{SecondsFormat, TimeZone, Utc};
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -13,24 +22,192 @@ pub trait Generator {
     fn generate(&self, logs: &[LogInfo]) -> Self::Message;
 }
 
-pub struct TextGenerator {}
-pub struct WebhookGenerator {}
+// summarises a whole session, `logs` being the kills in it
+pub trait SessionGenerator: Generator {
+    fn generate_session(&self, session: &Session, logs: &[LogInfo]) -> Self::Message;
+
+    // a single compact message that's kept up to date while the session goes on
+    fn generate_live(&self, session: &Session, logs: &[LogInfo]) -> Self::Message;
+}
+
+pub trait ReportGenerator: Generator {
+    fn generate_report(&self, report: &Report) -> Self::Message;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Default)]
+pub struct TextGenerator {
+    pub templates: Templates,
+}
+#[derive(Default)]
+pub struct WebhookGenerator {
+    pub mentions: Mentions,
+    pub templates: Templates,
+}
+pub struct JsonGenerator {}
+
+// how messages to a destination look
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Style {
+    // an embed per log
+    #[default]
+    Embed,
+    // a line per log
+    Text,
+}
+
+impl Style {
+    pub fn is_embed(&self) -> bool {
+        *self == Self::Embed
+    }
+}
+
+impl std::str::FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "embed" => Ok(Self::Embed),
+            "text" => Ok(Self::Text),
+            _ => Err(format!("unknown style `{}`, expected embed or text", s)),
+        }
+    }
+}
+
+impl Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Embed => write!(f, "embed"),
+            Self::Text => write!(f, "text"),
+        }
+    }
+}
+
+// a discord role or user, written as `role:<id>` or `user:<id>` or copied as
+// is from discord, i.e. <@&123> or <@123>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mention {
+    Role(String),
+    User(String),
+}
+
+impl std::str::FromStr for Mention {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mention = if let Some(id) = s.strip_prefix("role:") {
+            Self::Role(id.to_string())
+        } else if let Some(id) = s.strip_prefix("user:") {
+            Self::User(id.to_string())
+        } else if let Some(id) = s.strip_prefix("<@&").and_then(|s| s.strip_suffix('>')) {
+            Self::Role(id.to_string())
+        } else if let Some(id) = s.strip_prefix("<@").and_then(|s| s.strip_suffix('>')) {
+            Self::User(id.trim_start_matches('!').to_string())
+        } else {
+            return Err(format!(
+                "unknown mention `{}`, expected role:<id>, user:<id>, <@&id> or <@id>",
+                s
+            ));
+        };
+        match &mention {
+            Self::Role(id) | Self::User(id) if id.chars().all(|c| c.is_ascii_digit()) => {
+                Ok(mention)
+            }
+            _ => Err(format!("`{}` isn't a discord id", s)),
+        }
+    }
+}
+
+impl Display for Mention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Role(id) => write!(f, "<@&{}>", id),
+            Self::User(id) => write!(f, "<@{}>", id),
+        }
+    }
+}
+
+// who discord lets a message ping, see
+// https://discord.com/developers/docs/resources/channel#allowed-mentions-object
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AllowedMentions {
+    // kinds of mentions that always ping: roles, users or everyone
+    pub parse: Vec<String>,
+    pub roles: Vec<String>,
+    pub users: Vec<String>,
+}
+
+// who gets pinged about what
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mentions {
+    // kills that beat the fastest recorded one
+    pub records: Vec<Mention>,
+    pub wipes: Vec<Mention>,
+}
+
+impl Mentions {
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty() && self.wipes.is_empty()
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = &Mention> {
+        self.records.iter().chain(&self.wipes)
+    }
+
+    // only lets the mentions configured here ping anyone
+    pub fn allowed(&self) -> AllowedMentions {
+        let mut allowed = AllowedMentions::default();
+        for mention in self.all() {
+            let (ids, id) = match mention {
+                Mention::Role(id) => (&mut allowed.roles, id),
+                Mention::User(id) => (&mut allowed.users, id),
+            };
+            if !ids.contains(id) {
+                ids.push(id.clone());
+            }
+        }
+        allowed
+    }
+
+    // everyone that should hear about `logs`, each once
+    fn of(&self, logs: &[LogInfo]) -> String {
+        let record = logs.iter().any(|log| {
+            log.encounter.success && log.comparison.as_ref().is_some_and(Comparison::is_best)
+        });
+        let wipe = logs.iter().any(|log| !log.encounter.success);
+
+        let records = self.records.iter().filter(|_| record);
+        let wipes = self.wipes.iter().filter(|_| wipe);
+        let mut mentions: Vec<&Mention> = Vec::new();
+        for mention in records.chain(wipes) {
+            if !mentions.contains(&mention) {
+                mentions.push(mention);
+            }
+        }
+        mentions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
 
 impl Generator for TextGenerator {
     type Message = Text;
 
     fn generate(&self, logs: &[LogInfo]) -> Text {
         let content = logs.iter().fold(String::new(), |acc, log| {
-            let duration = fmt_time2(log.encounter.phases[0].duration());
-            let (success, sur) = if log.encounter.success {
-                ("Success", "**")
-            } else {
-                ("Defeat", "**")
-            };
-            format!(
-                "{}\n{} - {}{}{} in {}",
-                acc, log.log.link, sur, success, sur, duration
-            )
+            let fields = fields(log, &self.templates.squad);
+            format!("{}\n{}", acc, self.templates.text.render(&fields))
         });
         Text {
             content,
@@ -44,13 +221,87 @@ impl Generator for WebhookGenerator {
     type Message = Webhook;
 
     fn generate(&self, logs: &[LogInfo]) -> Webhook {
-        logs.iter().fold(Webhook::default(), |acc, log| {
-            let embed = Embed::from_log(&log.log.link, &log.encounter);
-            acc.add_embed(embed)
+        let webhook = Webhook::default().with_content(&self.mentions.of(logs));
+        logs.iter().fold(webhook, |acc, log| {
+            let fields = fields(log, &self.templates.squad);
+            let color = if log.encounter.success {
+                self.templates.success_color
+            } else {
+                self.templates.failure_color
+            };
+            let embed = Embed::new(
+                self.templates.title.render(&fields),
+                self.templates.description.render(&fields),
+                log.log.link.clone(),
+                color,
+            );
+            acc.add_embed(embed.with_details(log, &self.templates))
         })
     }
 }
 
+impl SessionGenerator for TextGenerator {
+    fn generate_session(&self, session: &Session, logs: &[LogInfo]) -> Text {
+        let mut text = self.generate(logs);
+        text.content = format!("{}\n{}", describe_session(session), text.content);
+        text
+    }
+
+    fn generate_live(&self, session: &Session, logs: &[LogInfo]) -> Text {
+        Text {
+            content: format!("{}{}", describe_session(session), describe_kills(logs)),
+            username: "snek".to_string(),
+            avatar_url: "https://i.imgur.com/IizO35l.png".to_string(),
+        }
+    }
+}
+
+impl SessionGenerator for WebhookGenerator {
+    fn generate_session(&self, session: &Session, logs: &[LogInfo]) -> Webhook {
+        let mut content = describe_session(session);
+        let mentions = self.mentions.of(logs);
+        if !mentions.is_empty() {
+            content = format!("{}\n{}", mentions, content);
+        }
+        self.generate(logs).with_content(&content)
+    }
+
+    fn generate_live(&self, session: &Session, logs: &[LogInfo]) -> Webhook {
+        let description = format!("{}{}", describe_session(session), describe_kills(logs));
+        let color = self.templates.success_color;
+        let embed = Embed::new("Tonight's run".to_string(), description, None, color);
+        Webhook::new().add_embed(embed)
+    }
+}
+
+impl ReportGenerator for TextGenerator {
+    fn generate_report(&self, report: &Report) -> Text {
+        Text {
+            content: describe_report(report),
+            username: "snek".to_string(),
+            avatar_url: "https://i.imgur.com/IizO35l.png".to_string(),
+        }
+    }
+}
+
+impl ReportGenerator for WebhookGenerator {
+    fn generate_report(&self, report: &Report) -> Webhook {
+        Webhook::new().with_content(&describe_report(report))
+    }
+}
+
+impl Generator for JsonGenerator {
+    type Message = Json;
+
+    fn generate(&self, logs: &[LogInfo]) -> Json {
+        Json::new(
+            logs.iter()
+                .map(|log| Summary::new(&log.log.log, log.log.link.as_deref(), &log.encounter))
+                .collect(),
+        )
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct Text {
     username: String,
@@ -64,6 +315,98 @@ impl Display for Text {
     }
 }
 
+// machine-readable description of a single encounter
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    target: Target,
+    name: String,
+    file: String,
+    link: Option<String>,
+    success: bool,
+    duration: u64,
+    phases: Vec<PhaseSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhaseSummary {
+    name: Option<String>,
+    duration: u64,
+}
+
+impl Summary {
+    pub fn new(log: &Log, link: Option<&str>, encounter: &Encounter) -> Self {
+        let phases = encounter
+            .phases
+            .iter()
+            .skip(1)
+            .map(|phase| PhaseSummary {
+                name: phase.name.clone(),
+                duration: phase.duration(),
+            })
+            .collect();
+
+        Self {
+            target: encounter.target,
+            name: encounter.target.to_string(),
+            file: log.file_name(),
+            link: link.map(str::to_owned),
+            success: encounter.success,
+            duration: encounter.phases[0].duration(),
+            phases,
+        }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let success = if self.success { "Success" } else { "Defeat" };
+        write!(
+            f,
+            "{} - {}: {} in {}",
+            self.file,
+            self.name,
+            success,
+            fmt_time3(self.duration)
+        )?;
+
+        for (idx, phase) in self.phases.iter().enumerate() {
+            let sep = if idx == 0 { " (" } else { " - " };
+            let time = fmt_time2(phase.duration);
+            match &phase.name {
+                Some(name) => write!(f, "{}{}: {}", sep, name, time)?,
+                None => write!(f, "{}{}: {}", sep, idx + 1, time)?,
+            }
+        }
+
+        if !self.phases.is_empty() {
+            write!(f, ")")?;
+        }
+
+        if let Some(link) = &self.link {
+            write!(f, " {}", link)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+pub struct Json(Vec<Summary>);
+
+impl Json {
+    pub fn new(summaries: Vec<Summary>) -> Self {
+        Self(summaries)
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_string_pretty(self).map_err(|_| fmt::Error)?;
+        write!(f, "{}", json)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Webhook {
     content: String,
@@ -77,8 +420,19 @@ impl Display for Webhook {
         write!(f, "{}", self.content)?;
         for embed in &self.embeds {
             writeln!(f)?;
-            writeln!(f, "{} ({})", embed.title, embed.url)?;
+            match &embed.url {
+                Some(url) => writeln!(f, "{} ({})", embed.title, url)?,
+                None => writeln!(f, "{}", embed.title)?,
+            }
             writeln!(f, "{}", embed.description.replace("*", ""))?;
+            for field in &embed.fields {
+                let sep = if field.value.contains('\n') {
+                    "\n"
+                } else {
+                    " "
+                };
+                writeln!(f, "{}:{}{}", field.name, sep, field.value.replace("*", ""))?;
+            }
         }
         Ok(())
     }
@@ -89,10 +443,6 @@ impl Webhook {
         Self::default()
     }
 
-    pub fn from_data(link: &str, encounter: &Encounter) -> Self {
-        Self::new().add_embed(Embed::from_log(link, encounter))
-    }
-
     pub fn with_content(mut self, content: &str) -> Self {
         self.content = content.to_owned();
         self
@@ -115,53 +465,241 @@ impl Default for Webhook {
     }
 }
 
+// see https://discord.com/developers/docs/resources/channel#embed-object
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Embed {
     title: String,
     description: String,
-    url: String,
+    // the title only links somewhere if the log was uploaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
     color: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<Field>,
+    // ISO 8601, shown in the reader's timezone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    footer: Option<Footer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<Author>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thumbnail: Option<Image>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image: Option<Image>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Field {
+    name: String,
+    value: String,
+    // side by side with other inline fields, up to 3 to a row
+    #[serde(default)]
+    inline: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Footer {
+    text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Author {
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Image {
+    url: String,
 }
 
 impl Embed {
-    pub const fn new(title: String, description: String, url: String, color: u32) -> Self {
+    pub const fn new(title: String, description: String, url: Option<String>, color: u32) -> Self {
         Self {
             title,
             description,
             url,
             color,
+            fields: Vec::new(),
+            timestamp: None,
+            footer: None,
+            author: None,
+            thumbnail: None,
+            image: None,
         }
     }
 
-    pub fn from_log(link: &str, encounter: &Encounter) -> Self {
-        let color = if encounter.success {
-            0x15_83_d1
-        } else {
-            0xd1_3e_15
-        };
-        Self::new(
-            encounter.target.to_string(),
-            describe(encounter),
-            link.to_string(),
-            color,
-        )
+    pub fn add_field(mut self, name: &str, value: &str, inline: bool) -> Self {
+        self.fields.push(Field {
+            name: name.to_string(),
+            value: value.to_string(),
+            inline,
+        });
+        self
+    }
+
+    // everything known about `log` that doesn't fit in the description
+    fn with_details(mut self, log: &LogInfo, templates: &Templates) -> Self {
+        let encounter = &log.encounter;
+        let squad = &templates.squad;
+        let names = squad.names(&log.log.players, log.log.recorder.as_deref());
+        for field in &templates.embed_fields {
+            match field {
+                EmbedField::Phases => {
+                    let (phases, splits) = split_phases(encounter);
+                    for (idx, phase) in phases.iter().enumerate().skip(1) {
+                        let name = phase
+                            .name
+                            .clone()
+                            .unwrap_or_else(|| format!("Phase {}", idx));
+                        self = self.add_field(&name, &fmt_time2(phase.duration()), true);
+                    }
+                    for (idx, split) in splits.iter().enumerate() {
+                        let name = format!("Split {}", idx + 1);
+                        self = self.add_field(&name, &fmt_time2(split.duration()), true);
+                    }
+                }
+                EmbedField::Squad if !names.is_empty() => {
+                    self = self.add_field("Squad", &describe_squad(log.log, squad), false);
+                }
+                EmbedField::Dps if !log.log.damage.is_empty() => {
+                    let secs = (encounter.phases[0].duration() as f64 / 1000.0).max(1.0);
+                    let top: Vec<String> = log
+                        .log
+                        .damage
+                        .iter()
+                        .filter_map(|player| Some((names.get(&player.player)?, player.damage)))
+                        .take(5)
+                        .enumerate()
+                        .map(|(idx, (name, damage))| {
+                            let dps = (damage as f64 / secs).round() as u64;
+                            format!("{}. {} - {}", idx + 1, name, dps)
+                        })
+                        .collect();
+                    if !top.is_empty() {
+                        self = self.add_field("DPS", &top.join("\n"), false);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.timestamp = log
+            .log
+            .start
+            .and_then(|start| Utc.timestamp_opt(start, 0).single())
+            .map(|start| start.to_rfc3339_opts(SecondsFormat::Secs, true));
+        let footer: Vec<String> = log
+            .log
+            .gw2_build
+            .map(|build| format!("GW2 build {}", build))
+            .into_iter()
+            .chain(
+                log.log
+                    .recorder
+                    .as_ref()
+                    .and_then(|pov| names.get(pov))
+                    .map(|pov| format!("recorded by {}", pov)),
+            )
+            .collect();
+        if !footer.is_empty() {
+            self.footer = Some(Footer {
+                text: footer.join(" - "),
+            });
+        }
+        self.author = encounter.target.fractal().map(|fractal| Author {
+            name: format!("{} CM", fractal),
+        });
+        self.thumbnail = encounter.target.thumbnail().map(|url| Image {
+            url: url.to_string(),
+        });
+        // charts are attached along with the message, discord doesn't show
+        // svgs in embeds though
+        self.image = log
+            .chart
+            .as_deref()
+            .filter(|chart| chart.extension().is_some_and(|ext| ext == "png"))
+            .and_then(|chart| chart.file_name())
+            .map(|name| Image {
+                url: format!("attachment://{}", name.to_string_lossy()),
+            });
+        self
     }
 }
 
-fn describe(encounter: &Encounter) -> String {
-    let status_line = status_msg(encounter);
+// who was there and what they played, i.e. `One.1234 (Firebrand)`
+fn describe_squad(log: &UploadedLog, squad: &Squad) -> String {
+    let names = squad.names(&log.players, log.recorder.as_deref());
+    let players: Vec<String> = log
+        .players
+        .iter()
+        .filter_map(|player| {
+            let name = names.get(player)?;
+            let spec = log.specs.get(player).copied();
+            let mut desc = match spec.and_then(|spec| squad.emoji(spec)) {
+                Some(emoji) => format!("{} {}", emoji, name),
+                None => name.clone(),
+            };
+            if let Some(spec) = spec {
+                desc += &format!(" ({})", spec);
+            }
+            Some(desc)
+        })
+        .collect();
+    players.join(", ")
+}
 
-    let mut phase_line = if encounter.phases[0].name.is_some() {
-        "".to_string()
+// what templates can refer to about `log`, see `template::FIELDS`
+fn fields(log: &LogInfo, squad: &Squad) -> HashMap<&'static str, String> {
+    let encounter = &log.encounter;
+    let duration = encounter.phases[0].duration();
+    let status = if encounter.success {
+        "Success"
     } else {
-        "Phases - ".to_string()
+        "Defeat"
     };
+    let comparison = log.comparison.as_ref();
+
+    let mut fields = HashMap::new();
+    fields.insert("target", encounter.target.to_string());
+    // logs that weren't uploaded go by their file name instead
+    fields.insert(
+        "link",
+        log.log
+            .link
+            .clone()
+            .unwrap_or_else(|| log.log.log.file_name()),
+    );
+    fields.insert("file", log.log.log.file_name());
+    fields.insert("status", status.to_string());
+    fields.insert("duration", fmt_time3(duration));
+    fields.insert("duration_short", fmt_time2(duration));
+    fields.insert("phases", describe_phases(encounter));
+    let names = squad.names(&log.log.players, log.log.recorder.as_deref());
+    let players: Vec<&str> = log
+        .log
+        .players
+        .iter()
+        .filter_map(|player| Some(names.get(player)?.as_str()))
+        .collect();
+    fields.insert("players", players.join(", "));
+    fields.insert("squad", describe_squad(log.log, squad));
+    fields.insert(
+        "pb_delta",
+        comparison.map(describe_comparison).unwrap_or_default(),
+    );
+    fields.insert(
+        "splits",
+        comparison.map(describe_splits).unwrap_or_default(),
+    );
+    fields
+}
 
+// the phases worth listing, the whole fight first, and the ones listed as
+// splits after them
+fn split_phases(encounter: &Encounter) -> (Vec<&Phase>, Vec<&Phase>) {
     let mut extra_phases: Vec<&Phase> = Vec::new();
 
-    use crate::parse::Phase;
-    use crate::target::Target;
-
     let phases = match encounter.target {
         Target::Siax => match encounter.phases.len() {
             1 => encounter.phases.iter().collect(),
@@ -183,6 +721,16 @@ fn describe(encounter: &Encounter) -> String {
         },
         _ => encounter.phases.iter().collect(),
     };
+    (phases, extra_phases)
+}
+
+fn describe_phases(encounter: &Encounter) -> String {
+    let mut phase_line = if encounter.phases[0].name.is_some() {
+        "".to_string()
+    } else {
+        "Phases - ".to_string()
+    };
+    let (phases, extra_phases) = split_phases(encounter);
 
     for (idx, phase) in phases.iter().enumerate().skip(1) {
         let (sep, sur) = match idx {
@@ -218,10 +766,9 @@ fn describe(encounter: &Encounter) -> String {
     }
 
     format!(
-        "{}\n{}{}",
-        status_line,
+        "{}{}",
         phase_line,
-        if extra_phases.len() > 0 {
+        if !extra_phases.is_empty() {
             extra
         } else {
             "".to_string()
@@ -229,18 +776,150 @@ fn describe(encounter: &Encounter) -> String {
     )
 }
 
-fn status_msg(encounter: &Encounter) -> String {
-    let success = if encounter.success {
-        "Success"
-    } else {
-        "Defeat"
-    };
+fn describe_comparison(comparison: &Comparison) -> String {
+    let delta = comparison.delta().map(fmt_delta);
+    match (delta, comparison.squad_delta()) {
+        (None, _) => "**New PB!**".to_string(),
+        (Some(delta), _) if comparison.is_best() => format!("**New PB!** {}", delta),
+        // a squad's first kill isn't worth pointing out, only improvements
+        (Some(delta), Some(squad_delta)) if comparison.is_squad_best() => format!(
+            "**New squad PB!** {} ({} vs PB)",
+            fmt_delta(squad_delta),
+            delta
+        ),
+        (Some(delta), _) => format!("{} vs PB", delta),
+    }
+}
+
+// one line per phase delta against the reference and one for the sum of best,
+// each starting with a newline
+fn describe_splits(comparison: &Comparison) -> String {
+    let mut content = String::new();
+    if let Some(reference) = &comparison.reference {
+        let splits: Vec<String> = reference
+            .splits
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, split)| {
+                let delta = split.delta()?;
+                let name = split.name.clone().unwrap_or_else(|| (idx + 1).to_string());
+                // gained time stands out
+                let sur = if delta < 0 { "**" } else { "" };
+                Some(format!("{}: {}{}{}", name, sur, fmt_delta(delta), sur))
+            })
+            .collect();
+        if !splits.is_empty() {
+            let against = if reference.pinned { "pin" } else { "PB" };
+            content.push_str(&format!("\nSplits vs {} - {}", against, splits.join(" - ")));
+        }
+    }
+    if let Some(sum) = comparison.sum_of_best {
+        content.push_str(&format!("\nSum of best: {}", fmt_time3(sum)));
+    }
+    content
+}
+
+fn describe_session(session: &Session) -> String {
+    let date = session
+        .date()
+        .map_or_else(String::new, |date| date.format(" %Y-%m-%d").to_string());
+    let duration = (session.end() - session.start()).max(0) as u64;
+    let mut content = format!(
+        "**Session{}** - {} in {}",
+        date,
+        plural(session.pulls.len(), "pull"),
+        fmt_hours(duration)
+    );
+
+    for run in session.runs() {
+        let pulls = format!(
+            "{}, {}",
+            plural(run.pulls, "pull"),
+            plural(run.wipes, "wipe")
+        );
+        match run.clear_time() {
+            Some(time) => content.push_str(&format!(
+                "\n{}: cleared in **{}** ({})",
+                run.fractal,
+                fmt_time3(time),
+                pulls
+            )),
+            None => content.push_str(&format!("\n{}: not cleared ({})", run.fractal, pulls)),
+        }
+    }
 
-    let duration = fmt_time3(encounter.phases[0].duration());
-    format!("**{}** in {}", success, duration)
+    content
+}
+
+// one line per kill, linking to it if it was uploaded
+fn describe_kills(logs: &[LogInfo]) -> String {
+    logs.iter().fold(String::new(), |mut acc, log| {
+        let target = match &log.log.link {
+            Some(link) => format!("[{}]({})", log.encounter.target, link),
+            None => log.encounter.target.to_string(),
+        };
+        let duration = fmt_time2(log.encounter.phases[0].duration());
+        acc.push_str(&format!("\n{} - **{}**", target, duration));
+        if let Some(comparison) = &log.comparison {
+            acc.push_str(&format!(" - {}", describe_comparison(comparison)));
+        }
+        acc
+    })
 }
 
-fn fmt_time2(time: u64) -> String {
+fn describe_report(report: &Report) -> String {
+    let mut content = format!(
+        "**Report {} - {}** - {}",
+        report.since.format("%Y-%m-%d"),
+        report.until.format("%Y-%m-%d"),
+        plural(report.attempts, "attempt")
+    );
+
+    if report.bosses.is_empty() {
+        content.push_str("\nno logs recorded in this period");
+        return content;
+    }
+
+    for boss in &report.bosses {
+        content.push_str(&format!(
+            "\n**{}** - {}, {}",
+            boss.target,
+            plural(boss.kills, "kill"),
+            plural(boss.wipes, "wipe")
+        ));
+        if let Some(fastest) = boss.fastest {
+            content.push_str(&format!(" - fastest **{}**", fmt_time3(fastest)));
+        }
+        if let Some(average) = boss.average {
+            content.push_str(&format!(" - average {}", fmt_time3(average)));
+            if let Some(previous) = boss.previous_average {
+                let delta = average as i64 - previous as i64;
+                content.push_str(&format!(" ({})", fmt_delta(delta)));
+            }
+        }
+    }
+
+    if !report.mechanics.is_empty() {
+        let mechanics: Vec<String> = report
+            .mechanics
+            .iter()
+            .map(|mechanic| format!("{} ({})", mechanic.name, mechanic.deaths))
+            .collect();
+        content.push_str(&format!("\nMost deaths: {}", mechanics.join(", ")));
+    }
+
+    content
+}
+
+fn plural(n: usize, word: &str) -> String {
+    if n == 1 {
+        format!("{} {}", n, word)
+    } else {
+        format!("{} {}s", n, word)
+    }
+}
+
+pub(crate) fn fmt_time2(time: u64) -> String {
     if time >= 60000 {
         let mins = time / 60000;
         let secs = (time % 60000) / 1000;
@@ -253,7 +932,21 @@ fn fmt_time2(time: u64) -> String {
     }
 }
 
-fn fmt_time3(time: u64) -> String {
+// for longer stretches of time where the milliseconds don't matter
+fn fmt_hours(time: u64) -> String {
+    let hours = time / 3_600_000;
+    let mins = (time % 3_600_000) / 60000;
+    let secs = (time % 60000) / 1000;
+    format!("{}:{:02}:{:02}", hours, mins, secs)
+}
+
+// signed difference between two times, i.e. -3.200s
+pub(crate) fn fmt_delta(delta: i64) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{}{}", sign, fmt_time3(delta.unsigned_abs()))
+}
+
+pub(crate) fn fmt_time3(time: u64) -> String {
     if time >= 60000 {
         let mins = time / 60000;
         let secs = (time % 60000) / 1000;
@@ -1,9 +1,12 @@
 use super::evtc::RawEvent;
 use crate::parse::evtc::{Agent, AgentId, Time};
 
-#[derive(Debug, Clone)]
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Event {
     pub time: Time,
+    #[serde(flatten)]
     pub kind: EventKind,
 }
 
@@ -12,10 +15,19 @@ macro_rules! events {
     (@s) => {};
 
     (@r $($name:ident)* $(,)?) => {
-        #[derive(Debug, Clone, Eq, PartialEq)]
+        #[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+        #[serde(tag = "kind")]
         pub enum EventKind {
             $($name($name)),*
         }
+
+        impl EventKind {
+            pub const fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$name(_) => stringify!($name)),*
+                }
+            }
+        }
     };
 
     (@r $($name:ident)*, $thing:ident$(($($item:ident: $type:ty),*))? $(, $($tail:tt)*)? ) => {
@@ -23,7 +35,7 @@ macro_rules! events {
     };
 
     (@s $name:ident$(($($item:ident: $type:ty),*))? $(, $($tail:tt)*)? ) => {
-        #[derive(Debug, Clone, Eq, PartialEq)]
+        #[derive(Debug, Clone, Eq, PartialEq, Serialize)]
         pub struct $name {
             pub target: AgentId,
             $( $(pub $item: $type),* )?
@@ -54,6 +66,7 @@ events! {
     Reward(kind: i32, id: u16),
     Spawn,
     Despawn,
+    HealthUpdate(percent: u16), // in hundredths of a percent
 }
 
 // TODO: having the macro generate something along these lines would be good
@@ -172,10 +185,16 @@ impl EventKind {
             target: event.src_instid,
         })
     }
+
+    pub const fn health_update(event: RawEvent) -> Self {
+        Self::HealthUpdate(HealthUpdate {
+            target: event.src_instid,
+            percent: event.dst_agent as _,
+        })
+    }
 }
 
 // pretty printing for debugging
-#[cfg(debug_assertions)]
 impl Event {
     #[allow(dead_code)]
     pub fn pretty_print(
@@ -183,8 +202,16 @@ impl Event {
         agents: &crate::parse::evtc::AgentMap,
         skills: &crate::parse::evtc::SkillMap,
     ) {
+        println!("{}", self.pretty(agents, skills));
+    }
+
+    pub fn pretty(
+        &self,
+        agents: &crate::parse::evtc::AgentMap,
+        skills: &crate::parse::evtc::SkillMap,
+    ) -> String {
         match &self.kind {
-            EventKind::BuffApply(e) /* if e.id == 762 */ => println!(
+            EventKind::BuffApply(e) /* if e.id == 762 */ => format!(
                 //"{:6} {:>12} - {} -> {} for {}ms ({})",
                 "{:6} {:>12} - {} applied {} ({}) to {} for {}ms",
                 self.time,
@@ -197,7 +224,7 @@ impl Event {
 
                 e.duration,
             ),
-            EventKind::BuffRemove(e) /* if e.id == 762 */ => println!(
+            EventKind::BuffRemove(e) /* if e.id == 762 */ => format!(
                 "{:6} {:>12} - {} removed {} ({}) on {} x{}",
                 self.time,
                 "Buff Expire",
@@ -208,7 +235,7 @@ impl Event {
                 agents.pretty(&e.target),
                 e.stacks,
             ),
-            EventKind::CastStart(e) => println!(
+            EventKind::CastStart(e) => format!(
                 "{:6} {:>12} - {}: {} ({}ms - {}ms)", 
                 self.time,
                 "Cast Start", 
@@ -217,7 +244,7 @@ impl Event {
                 e.effect,
                 e.duration,
             ),
-            EventKind::CastCFire(e) => println!(
+            EventKind::CastCFire(e) => format!(
                 "{:6} {:>12} - {}: {} ({}ms - {}ms)",
                 self.time,
                 "Cast CFire",
@@ -226,7 +253,7 @@ impl Event {
                 e.animation,
                 e.scaled,
             ),
-            EventKind::CastCancel(e) => println!(
+            EventKind::CastCancel(e) => format!(
                 "{:6} {:>12} - {}: {} ({}ms - {}ms)",
                 self.time,
                 "Cast Cancel",
@@ -235,23 +262,26 @@ impl Event {
                 e.animation,
                 e.scaled,
             ),
-            EventKind::CastEnd(e) => println!(
+            EventKind::CastEnd(e) => format!(
                 "{:6} {:>12} - {}: {}",
                 self.time,
                 "Cast End",
                 agents.pretty(&e.target),
                 skills.pretty(&e.skill),
             ),
-            EventKind::CombatEnter(e) => println!(
+            EventKind::CombatEnter(e) => format!(
                 "{:6} {:>12} - {}",
                 self.time,
                 "Combat Enter",
                 agents.pretty(&e.target)
             ),
-            EventKind::CombatExit(e) => {
-                println!("{:6} {:>12} - {}", self.time, "Combat Exit",  agents.pretty(&e.target))
-            }
-            EventKind::CondDamage(e) => println!(
+            EventKind::CombatExit(e) => format!(
+                "{:6} {:>12} - {}",
+                self.time,
+                "Combat Exit",
+                agents.pretty(&e.target)
+            ),
+            EventKind::CondDamage(e) => format!(
                 "{:6} {:>12} - {} -> {}, Skill: {}, Dmg: {}",
                 self.time,
                 "Condition",
@@ -260,7 +290,7 @@ impl Event {
                 skills.pretty(&e.skill),
                 e.dmg
             ),
-            EventKind::PhysDamage(e) => println!(
+            EventKind::PhysDamage(e) => format!(
                 "{:6} {:>12} - {} -> {}, Skill: {} ({}), Dmg: {}",
                 self.time,
                 "Physical",
@@ -270,27 +300,26 @@ impl Event {
                 e.skill,
                 e.dmg
             ),
-            EventKind::WeaponSwap(e) => println!(
+            EventKind::WeaponSwap(e) => format!(
                 "{:6} {:>12} - {} to set {}",
                 self.time,
                 "Swap",
                 agents.pretty(&e.target),
                 e.set
             ),
-            EventKind::Death(e) => println!("{:6} {:>12} - {}", self.time, "Death", agents.pretty(&e.target)),
-            EventKind::Reward(e) => println!("{:6} {:>12} - {} ({}), kind: {}, id: {}", self.time, "Reward", agents.pretty(&e.target), e.target, e.kind, e.id),
-            EventKind::Spawn(e) => println!("{:6} {:>12} - {}", self.time, "Spawn", agents.pretty(&e.target)),
-            EventKind::Despawn(e) => println!("{:6} {:>12} - {}", self.time, "Despawn", agents.pretty(&e.target)),
+            EventKind::Death(e) => format!("{:6} {:>12} - {}", self.time, "Death", agents.pretty(&e.target)),
+            EventKind::Reward(e) => format!("{:6} {:>12} - {} ({}), kind: {}, id: {}", self.time, "Reward", agents.pretty(&e.target), e.target, e.kind, e.id),
+            EventKind::Spawn(e) => format!("{:6} {:>12} - {}", self.time, "Spawn", agents.pretty(&e.target)),
+            EventKind::Despawn(e) => format!("{:6} {:>12} - {}", self.time, "Despawn", agents.pretty(&e.target)),
+            EventKind::HealthUpdate(e) => format!("{:6} {:>12} - {} at {:.2}%", self.time, "Health", agents.pretty(&e.target), e.percent as f64 / 100.0),
         }
     }
 }
 
-#[cfg(debug_assertions)]
 pub trait Pretty<K> {
     fn pretty(&self, k: &K) -> String;
 }
 
-#[cfg(debug_assertions)]
 impl<K> Pretty<K> for std::collections::HashMap<K, Agent>
 where
     K: std::cmp::Eq + std::hash::Hash + std::fmt::Display,
@@ -302,7 +331,6 @@ where
     }
 }
 
-#[cfg(debug_assertions)]
 impl<K> Pretty<K> for std::collections::HashMap<K, String>
 where
     K: std::cmp::Eq + std::hash::Hash + std::fmt::Display,
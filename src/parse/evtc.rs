@@ -6,9 +6,13 @@ use std::{
     slice,
 };
 
+use serde::Serialize;
+
 use super::event;
+use crate::spec::Spec;
 
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(transparent)]
 pub struct AgentId(u16);
 pub type AgentMap = HashMap<AgentId, Agent>;
 pub type SkillMap = HashMap<i32, String>;
@@ -26,6 +30,8 @@ pub struct Agent {
     pub health: u64,
     pub first_aware: Time,
     pub last_aware: Time,
+    // only known for players
+    pub spec: Option<Spec>,
 }
 
 pub struct Data {
@@ -34,6 +40,30 @@ pub struct Data {
     pub players: AgentMap,
     pub skills: SkillMap,
     pub events: Vec<event::Event>,
+    pub meta: Metadata,
+}
+
+// details about the log itself rather than the fight
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Metadata {
+    pub arc_build: String,
+    pub gw2_build: Option<u64>,
+    pub language: Option<u64>,
+    pub map_id: Option<u64>,
+    pub shard_id: Option<u64>,
+    // unix timestamps according to the server
+    pub log_start: Option<i64>,
+    pub log_end: Option<i64>,
+    // account name of the player who recorded the log
+    pub pov: Option<String>,
+}
+
+// a player going down for good, along with whatever hit them last
+#[derive(Debug, Clone)]
+pub struct PlayerDeath {
+    pub time: Time,
+    pub player: String,
+    pub skill: Option<String>,
 }
 
 impl Data {
@@ -46,9 +76,102 @@ impl Data {
             }
         })
     }
+
+    // damage each player did to the boss, most first
+    pub fn player_damage(&self) -> Vec<(String, u64)> {
+        use event::{CondDamage, EventKind, PhysDamage};
+
+        let mut damage: HashMap<AgentId, u64> = HashMap::new();
+        for event in &self.events {
+            match &event.kind {
+                EventKind::PhysDamage(PhysDamage {
+                    target, src, dmg, ..
+                })
+                | EventKind::CondDamage(CondDamage {
+                    target, src, dmg, ..
+                }) if *target == self.boss && *dmg > 0 && self.players.contains_key(src) => {
+                    *damage.entry(*src).or_default() += *dmg as u64;
+                }
+                _ => {}
+            }
+        }
+
+        let mut damage: Vec<(String, u64)> = damage
+            .into_iter()
+            .map(|(id, damage)| (self.players[&id].name.clone(), damage))
+            .collect();
+        damage.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        damage
+    }
+
+    // how much health the boss had left over time in percent, at most a
+    // second apart
+    pub fn boss_health(&self) -> Vec<(Time, f64)> {
+        use event::{EventKind, HealthUpdate};
+
+        let mut health: Vec<(Time, f64)> = Vec::new();
+        let updates = self.events.iter().filter_map(|event| match &event.kind {
+            EventKind::HealthUpdate(HealthUpdate { target, percent }) if *target == self.boss => {
+                Some((event.time, *percent as f64 / 100.0))
+            }
+            _ => None,
+        });
+        // the latest update is kept too, so the boss doesn't stop short of 0
+        let mut latest = None;
+        for (time, percent) in updates {
+            match health.last() {
+                Some(&(kept, _)) if time < kept + 1000 => latest = Some((time, percent)),
+                _ => {
+                    health.push((time, percent));
+                    latest = None;
+                }
+            }
+        }
+        health.extend(latest);
+        health
+    }
+
+    pub fn player_deaths(&self) -> Vec<PlayerDeath> {
+        use event::{CondDamage, Death, EventKind, PhysDamage};
+
+        let mut last_hits = HashMap::new();
+        let mut deaths = Vec::new();
+        for event in &self.events {
+            match &event.kind {
+                EventKind::PhysDamage(PhysDamage {
+                    target,
+                    src,
+                    dmg,
+                    skill,
+                })
+                | EventKind::CondDamage(CondDamage {
+                    target,
+                    src,
+                    dmg,
+                    skill,
+                }) if target != src && *dmg > 0 && self.players.contains_key(target) => {
+                    last_hits.insert(*target, *skill);
+                }
+                EventKind::Death(Death { target }) if self.players.contains_key(target) => {
+                    deaths.push(PlayerDeath {
+                        time: event.time,
+                        player: self.players[target].name.clone(),
+                        skill: last_hits
+                            .remove(target)
+                            .and_then(|skill| self.skills.get(&skill).cloned()),
+                    });
+                }
+                _ => {}
+            }
+        }
+        deaths
+    }
 }
 
+// raw structs are read straight from the file, so field order has to match
+#[allow(dead_code)]
 #[derive(Debug)]
+#[repr(C)]
 struct Header {
     evtc: [u8; 4],
     timestamp: [u8; 8],
@@ -58,6 +181,7 @@ struct Header {
 }
 
 #[allow(dead_code)]
+#[repr(C)]
 struct EvtcAgent {
     addr: u64,
     prof: u32,
@@ -71,12 +195,15 @@ struct EvtcAgent {
     name: [u8; 64],
 }
 
+#[repr(C)]
 struct Skill {
     id: i32,
     name: [u8; 64],
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
+#[repr(C)]
 pub struct RawEvent {
     pub time: Time,
     pub src_agent: u64,
@@ -119,7 +246,7 @@ pub fn parse(path: impl AsRef<Path>) -> Data {
 
 fn load_evtc(path: &Path) -> Vec<u8> {
     let extension = path.extension().and_then(|s| s.to_str()).unwrap();
-    let mut file = std::fs::File::open(&path).unwrap();
+    let mut file = std::fs::File::open(path).unwrap();
 
     let mut bytes = vec![];
     match extension {
@@ -159,6 +286,7 @@ unsafe fn parse_unchecked(mut rdr: impl ReadStruct) -> Data {
                 health: 0,
                 first_aware: 0,
                 last_aware: 0,
+                spec: Spec::from_ids(evtc_agent.prof, evtc_agent.is_elite),
             };
             players_by_addr.insert(evtc_agent.addr, agent);
         } else if boss_addr == u64::MAX {
@@ -170,6 +298,7 @@ unsafe fn parse_unchecked(mut rdr: impl ReadStruct) -> Data {
             health: 0,
             first_aware: u64::MAX,
             last_aware: 0,
+            spec: None,
         };
         agents_by_addr.insert(evtc_agent.addr, agent);
     }
@@ -179,13 +308,19 @@ unsafe fn parse_unchecked(mut rdr: impl ReadStruct) -> Data {
     skills.reserve(skill_count as _);
     for _ in 0..skill_count {
         let skill = rdr.read_struct::<Skill>().unwrap();
-        let skill_name = String::from_utf8_unchecked(skill.name.to_vec());
+        let skill_name = str_from_u8_nul_utf8_unchecked(&skill.name).to_string();
         skills.insert(skill.id, skill_name);
     }
 
+    let mut meta = Metadata {
+        arc_build: str_from_u8_nul_utf8_unchecked(&header.timestamp).to_string(),
+        ..Metadata::default()
+    };
+
     let mut events = Vec::new();
     let first_event = rdr.read_struct::<RawEvent>().unwrap();
-    println!("start time: {}", first_event.time);
+    log::trace!("start time: {}", first_event.time);
+    update_metadata(&mut meta, &first_event, &players_by_addr);
 
     let mut boss: AgentId = AgentId(0);
     let mut agents = AgentMap::new();
@@ -193,6 +328,7 @@ unsafe fn parse_unchecked(mut rdr: impl ReadStruct) -> Data {
 
     while let Ok(mut raw_event) = rdr.read_struct::<RawEvent>() {
         raw_event.time = raw_event.time.saturating_sub(first_event.time);
+        update_metadata(&mut meta, &raw_event, &players_by_addr);
 
         if let Some((id, instid)) = event_agent(&raw_event) {
             // Set instance IDs
@@ -236,6 +372,23 @@ unsafe fn parse_unchecked(mut rdr: impl ReadStruct) -> Data {
         players,
         skills,
         events,
+        meta,
+    }
+}
+
+fn update_metadata(meta: &mut Metadata, event: &RawEvent, players: &HashMap<u64, Agent>) {
+    use CbtStateChange::*;
+    match event.is_statechange {
+        x if x == LogStart as u8 => meta.log_start = Some(event.value as u32 as i64),
+        x if x == LogEnd as u8 => meta.log_end = Some(event.value as u32 as i64),
+        x if x == PointOfView as u8 => {
+            meta.pov = players.get(&event.src_agent).map(|a| a.name.clone())
+        }
+        x if x == Language as u8 => meta.language = Some(event.src_agent),
+        x if x == GwBuild as u8 => meta.gw2_build = Some(event.src_agent),
+        x if x == ShardId as u8 => meta.shard_id = Some(event.src_agent),
+        x if x == MapId as u8 => meta.map_id = Some(event.src_agent),
+        _ => {}
     }
 }
 
@@ -251,10 +404,10 @@ pub fn target_id(path: impl AsRef<Path>) -> Option<u16> {
         "zevtc" => {
             let mut archive = zip::ZipArchive::new(f).ok()?;
             let mut unzipped = archive.by_index(0).ok()?;
-            unzipped.read(&mut buffer[..]).ok()?;
+            unzipped.read_exact(&mut buffer[..]).ok()?;
         }
         "evtc" => {
-            f.read(&mut buffer[..]).ok()?;
+            f.read_exact(&mut buffer[..]).ok()?;
         }
         _ => panic!("trying to parse file with invalid extension: {:?}", path),
     };
@@ -300,6 +453,7 @@ impl RawEvent {
             4 => EventKind::death(self),
             6 => EventKind::spawn(self),
             7 => EventKind::despawn(self),
+            8 => EventKind::health_update(self),
             11 => EventKind::weapon_swap(self),
             17 => EventKind::reward(self),
             _ => return None,
@@ -420,9 +574,6 @@ impl std::fmt::Display for AgentId {
 }
 
 unsafe fn str_from_u8_nul_utf8_unchecked(src: &[u8]) -> &str {
-    let nul_range_end = src
-        .iter()
-        .position(|&c| c == b'\0')
-        .unwrap_or_else(|| src.len());
+    let nul_range_end = src.iter().position(|&c| c == b'\0').unwrap_or(src.len());
     std::str::from_utf8_unchecked(&src[0..nul_range_end])
 }
@@ -1,15 +1,19 @@
 mod event;
 mod evtc;
 
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap};
 
-use crate::{log, target::Target};
+use crate::{log::Log, target::Target};
+
+use serde::{Deserialize, Serialize};
 
 pub use event::Event;
 pub use event::EventKind;
 pub use evtc::target_id;
 pub use evtc::AgentId;
 pub use evtc::Data;
+pub use evtc::Metadata;
+pub use evtc::PlayerDeath;
 pub use evtc::Time;
 
 #[derive(Debug, Clone)]
@@ -19,7 +23,13 @@ pub struct Encounter {
     pub phases: Vec<Phase>,
 }
 
-pub fn parse(log: &log::Log) -> Option<(Vec<Encounter>, evtc::Data)> {
+pub fn parse(log: &Log) -> Option<(Vec<Encounter>, evtc::Data)> {
+    parse_with_trace(log).map(|(encounters, data, _)| (encounters, data))
+}
+
+// same as `parse`, but also returns the reasoning behind the success and phase
+// decisions that were made along the way
+pub fn parse_with_trace(log: &Log) -> Option<(Vec<Encounter>, evtc::Data, Trace)> {
     use Target::*;
     match log.target() {
         Mama | Siax | Enso | Skor | Arts | Arkk | Ai | Golem => {}
@@ -29,14 +39,17 @@ pub fn parse(log: &log::Log) -> Option<(Vec<Encounter>, evtc::Data)> {
     let data = evtc::parse(log.path());
 
     // make sure skorvald is cm via health check
-    if log.target() == Skor && data.agents.get(&data.boss).unwrap().health < 5526980 {
+    let health = data.agents.get(&data.boss).unwrap().health;
+    if log.target() == Skor && health < 5526980 {
+        log::debug!("skorvald has {} health, not a cm log", health);
         return None;
     }
 
-    let ctx = gather_context(&data, log.target());
+    let mut ctx = gather_context(&data, log.target());
 
     if log.target() == Target::Ai {
-        return Some((parse_ai(&data, ctx), data));
+        let encounters = parse_ai(&data, &mut ctx);
+        return Some((encounters, data, ctx.trace.into_inner()));
     }
 
     let phases = parse_phases(&ctx, log.target());
@@ -47,13 +60,59 @@ pub fn parse(log: &log::Log) -> Option<(Vec<Encounter>, evtc::Data)> {
         phases,
     };
 
-    Some((vec![encounter], data))
+    Some((vec![encounter], data, ctx.trace.into_inner()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Topic {
+    Start,
+    Success,
+    Phases,
+}
+
+impl Topic {
+    const fn target(self) -> &'static str {
+        match self {
+            Self::Start => "snek::parse::start",
+            Self::Success => "snek::parse::success",
+            Self::Phases => "snek::parse::phases",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Note {
+    pub topic: Topic,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct Trace(Vec<Note>);
+
+impl Trace {
+    fn push(&mut self, topic: Topic, message: String) {
+        log::debug!(target: topic.target(), "{}", message);
+        self.0.push(Note { topic, message });
+    }
+
+    pub fn notes(&self) -> &[Note] {
+        &self.0
+    }
 }
 
-fn parse_ai(data: &evtc::Data, ctx: LogContext) -> Vec<Encounter> {
+fn parse_ai(data: &evtc::Data, ctx: &mut LogContext) -> Vec<Encounter> {
     let dark_form_phase_event_time = ctx.casts.get(&53569);
-    let has_dark_form = ctx.casts.get(&61356).is_some();
+    let has_dark_form = ctx.casts.contains_key(&61356);
     let has_elemental_form = !has_dark_form || dark_form_phase_event_time.is_some();
+    ctx.note(
+        Topic::Phases,
+        format!(
+            "ai: elemental form: {}, dark form: {}",
+            has_elemental_form, has_dark_form
+        ),
+    );
 
     let mut offset = 0;
 
@@ -62,11 +121,10 @@ fn parse_ai(data: &evtc::Data, ctx: LogContext) -> Vec<Encounter> {
         offset = *ctx
             .casts
             .get(&61277)
-            .map(|v| {
+            .and_then(|v| {
                 v.iter()
-                    .find(|t| *t >= dark_form_phase_event_time.unwrap().get(0).unwrap())
+                    .find(|t| *t >= dark_form_phase_event_time.unwrap().first().unwrap())
             })
-            .flatten()
             .unwrap()
             + 1;
 
@@ -78,14 +136,15 @@ fn parse_ai(data: &evtc::Data, ctx: LogContext) -> Vec<Encounter> {
         {
             offset = invuln_loss.0 + 1;
         }
+
+        ctx.note(Topic::Start, format!("ai: dark form starts at {}", offset));
     }
 
     let mut encounters: Vec<Encounter> = Vec::new();
-    let mut ctx = ctx;
 
     if has_elemental_form {
         ctx.start = data.agents.get(&data.boss).unwrap().first_aware;
-        let elemental_phases = parse_phases_ai_elemental(&ctx);
+        let elemental_phases = parse_phases_ai_elemental(ctx);
         let elemental_encounter = Encounter {
             target: Target::AiElemental,
             success: ctx.success.is_some(),
@@ -98,12 +157,12 @@ fn parse_ai(data: &evtc::Data, ctx: LogContext) -> Vec<Encounter> {
     if has_dark_form {
         offset = offset.max(data.agents.get(&data.boss).unwrap().first_aware);
         ctx.success = if has_elemental_form {
-            check_success_ai(&ctx, offset)
+            check_success_ai(ctx, offset)
         } else {
             ctx.success
         };
 
-        let dark_phases = parse_phases_ai_dark(&ctx, offset);
+        let dark_phases = parse_phases_ai_dark(ctx, offset);
 
         let dark_encounter = Encounter {
             target: Target::AiDark,
@@ -116,8 +175,6 @@ fn parse_ai(data: &evtc::Data, ctx: LogContext) -> Vec<Encounter> {
     encounters
 }
 
-use serde::Deserialize;
-
 #[derive(Debug, Clone, Deserialize)]
 pub struct Phase {
     start: Time,
@@ -148,16 +205,14 @@ fn parse_phases_ai_dark(ctx: &LogContext, offset: Time) -> Vec<Phase> {
     if let Some(fear_to_sorrow) = ctx
         .casts
         .get(&61606)
-        .map(|v| v.iter().find(|t| **t >= offset))
-        .flatten()
+        .and_then(|v| v.iter().find(|t| **t >= offset))
     {
         phases.push((dark_form_start + 1 - offset, fear_to_sorrow - offset).into());
 
         if let Some(sorrow_to_guilt) = ctx
             .casts
             .get(&61602)
-            .map(|v| v.iter().find(|t| **t >= offset))
-            .flatten()
+            .and_then(|v| v.iter().find(|t| **t >= offset))
         {
             phases.push((fear_to_sorrow + 1 - offset, sorrow_to_guilt - offset).into());
             phases.push((sorrow_to_guilt + 1 - offset, dark_form_end - offset).into());
@@ -183,7 +238,7 @@ fn parse_phases_ai_elemental(ctx: &LogContext) -> Vec<Phase> {
     let invuln_gain_times = ctx
         .invuln_changes
         .iter()
-        .filter(|(_, is_gain)| (!*is_gain))
+        .filter(|(_, is_gain)| !*is_gain)
         .map(|(time, _)| *time)
         .collect::<Vec<_>>();
 
@@ -269,6 +324,15 @@ fn parse_phases_by_invulns(ctx: &LogContext, target: Target) -> Vec<Phase> {
         phases.append(&mut extra_phases);
     }
 
+    ctx.note(
+        Topic::Phases,
+        format!(
+            "{} invuln changes on target: {} phases",
+            ctx.invuln_changes.len(),
+            phases.len() - 1
+        ),
+    );
+
     phases
 }
 
@@ -278,6 +342,10 @@ impl Phase {
         self.end - self.start
     }
 
+    pub const fn start(&self) -> u64 {
+        self.start
+    }
+
     pub const fn end(&self) -> u64 {
         self.end
     }
@@ -299,7 +367,6 @@ struct LogContext {
     success: Option<Time>,
     players: Vec<AgentId>,
     start: Time,
-    end: Time,
     last_event: Time,
     last_aware: Time,
     first_reward: Option<Time>,
@@ -311,13 +378,23 @@ struct LogContext {
     spawns: HashMap<AgentId, Time>,
     deaths: HashMap<AgentId, Time>,
     casts: HashMap<i32, Vec<Time>>,
+    trace: RefCell<Trace>,
 }
 
+impl LogContext {
+    fn note(&self, topic: Topic, message: String) {
+        self.trace.borrow_mut().push(topic, message);
+    }
+}
+
+#[allow(clippy::collapsible_match, clippy::unwrap_or_default)]
 fn gather_context(data: &evtc::Data, target: Target) -> LogContext {
     use event::*;
 
-    let mut ctx = LogContext::default();
-    ctx.target = data.boss;
+    let mut ctx = LogContext {
+        target: data.boss,
+        ..LogContext::default()
+    };
 
     for (id, _) in data.players.iter() {
         ctx.players.push(*id);
@@ -337,7 +414,7 @@ fn gather_context(data: &evtc::Data, target: Target) -> LogContext {
                 target, src, dmg, ..
             }) => {
                 if target != src && target == ctx.target && dmg > 0 {
-                    println!("{} - {} dmg - {:?}", event.time, dmg, event.kind);
+                    log::trace!("{} - {} dmg - {:?}", event.time, dmg, event.kind);
                     ctx.last_dmg = Some(event.time)
                 }
             }
@@ -347,18 +424,14 @@ fn gather_context(data: &evtc::Data, target: Target) -> LogContext {
                     // if this is the second application in a row, ignore it
                     match id {
                         762 => {
-                            if let Some((_, kind)) = ctx.invuln_changes.last() {
-                                if !*kind {
-                                    continue;
-                                }
+                            if let Some((_, false)) = ctx.invuln_changes.last() {
+                                continue;
                             }
                             ctx.invuln_changes.push((event.time, false));
                         }
                         895 => {
-                            if let Some((_, kind)) = ctx.other_invuln_changes.last() {
-                                if !*kind {
-                                    continue;
-                                }
+                            if let Some((_, false)) = ctx.other_invuln_changes.last() {
+                                continue;
                             }
                             ctx.other_invuln_changes.push((event.time, false));
                         }
@@ -372,18 +445,14 @@ fn gather_context(data: &evtc::Data, target: Target) -> LogContext {
                     // if this is the second removal in a row, ignore it
                     match id {
                         762 => {
-                            if let Some((_, kind)) = ctx.invuln_changes.last() {
-                                if *kind {
-                                    continue;
-                                }
+                            if let Some((_, true)) = ctx.invuln_changes.last() {
+                                continue;
                             }
                             ctx.invuln_changes.push((event.time, true));
                         }
                         895 => {
-                            if let Some((_, kind)) = ctx.other_invuln_changes.last() {
-                                if *kind {
-                                    continue;
-                                }
+                            if let Some((_, true)) = ctx.other_invuln_changes.last() {
+                                continue;
                             }
                             ctx.other_invuln_changes.push((event.time, true));
                         }
@@ -429,12 +498,28 @@ fn gather_context(data: &evtc::Data, target: Target) -> LogContext {
     // removing from beginning here on a vec is inefficient, queue would be better
     // but this array is tiny anyway so does it really matter?
     if matches!(target, Target::Enso | Target::Mama) {
-        match ctx.invuln_changes.first() {
-            Some((_, r)) if *r => ctx.start = ctx.invuln_changes.remove(0).0 + 1,
-            Some((t, _)) if *t < 1500 => {
+        match ctx.invuln_changes.first().copied() {
+            Some((_, true)) => {
+                ctx.start = ctx.invuln_changes.remove(0).0 + 1;
+                ctx.note(
+                    Topic::Start,
+                    format!(
+                        "log starts with invuln removal: start moved to {}",
+                        ctx.start
+                    ),
+                );
+            }
+            Some((t, _)) if t < 1500 => {
                 ctx.invuln_changes.remove(0); // remove invuln gain
                 ctx.invuln_changes.remove(0); // and subsequent removal
-                ctx.start = ctx.combat_enters[&ctx.target] + 1
+                ctx.start = ctx.combat_enters[&ctx.target] + 1;
+                ctx.note(
+                    Topic::Start,
+                    format!(
+                        "early invuln at {}: start moved to combat enter at {}",
+                        t, ctx.start
+                    ),
+                );
             }
             _ => {}
         }
@@ -456,44 +541,105 @@ fn check_success_ai(ctx: &LogContext, start: Time) -> Option<Time> {
         .iter()
         .find(|(t, r)| *t >= start && !*r)
     {
+        ctx.note(
+            Topic::Success,
+            format!("success: invuln (895) gained at {} after {}", time, start),
+        );
         Some(*time)
     } else {
+        ctx.note(
+            Topic::Success,
+            format!("defeat: no invuln (895) gained after {}", start),
+        );
         None
     }
 }
 
 fn check_success(ctx: &LogContext) -> Option<Time> {
-    let dmg = ctx.last_dmg?;
+    let dmg = match ctx.last_dmg {
+        Some(dmg) => dmg,
+        None => {
+            ctx.note(Topic::Success, "defeat: target never took damage".into());
+            return None;
+        }
+    };
 
     if let Some(reward) = ctx.first_reward {
-        //println!("dmg: {}, reward: {}", dmg, reward);
         if (dmg as i64).saturating_sub(reward as i64).abs() < 100 {
+            ctx.note(
+                Topic::Success,
+                format!(
+                    "success: last damage at {} close to reward at {}",
+                    dmg, reward
+                ),
+            );
             return Some(std::cmp::min(dmg, reward));
         }
+        ctx.note(
+            Topic::Success,
+            format!("reward at {} too far from last damage at {}", reward, dmg),
+        );
     }
 
     if let Some(death) = ctx.deaths.get(&ctx.target) {
-        //println!("death: {}, dmg: {}", *death, dmg);
+        ctx.note(
+            Topic::Success,
+            format!("success: target died at {}, last damage at {}", death, dmg),
+        );
         return Some(std::cmp::min(*death, dmg));
     }
 
+    ctx.note(
+        Topic::Success,
+        format!("defeat: no reward or target death, last damage at {}", dmg),
+    );
     None
 }
 
 fn check_success_by_invuln_count(ctx: &LogContext, count: usize) -> Option<Time> {
-    let last_is_removal = ctx.invuln_changes.last().map(|(_, is)| *is)?;
+    let last_is_removal = match ctx.invuln_changes.last() {
+        Some((_, is_removal)) => *is_removal,
+        None => {
+            ctx.note(Topic::Success, "defeat: no invuln changes on target".into());
+            return None;
+        }
+    };
 
     if ctx.invuln_changes.len() == count && last_is_removal {
         check_success_by_combat_exit(ctx)
     } else {
+        ctx.note(
+            Topic::Success,
+            format!(
+                "defeat: {} invuln changes (last was {}), expected {} ending in removal",
+                ctx.invuln_changes.len(),
+                if last_is_removal { "removal" } else { "gain" },
+                count
+            ),
+        );
         None
     }
 }
 
 fn check_success_by_combat_exit(ctx: &LogContext) -> Option<Time> {
-    let last_dmg = ctx.last_dmg?;
-    let target_enter = *ctx.combat_enters.get(&ctx.target)?;
-    let target_exit = *ctx.combat_exits.get(&ctx.target)?;
+    let (last_dmg, target_enter, target_exit) = match (
+        ctx.last_dmg,
+        ctx.combat_enters.get(&ctx.target),
+        ctx.combat_exits.get(&ctx.target),
+    ) {
+        (Some(last_dmg), Some(enter), Some(exit)) => (last_dmg, *enter, *exit),
+        (last_dmg, enter, exit) => {
+            ctx.note(
+                Topic::Success,
+                format!(
+                    "defeat: missing last damage ({:?}), target combat enter ({:?}) or exit ({:?})",
+                    last_dmg, enter, exit
+                ),
+            );
+            return None;
+        }
+    };
+
     let player_exit = ctx
         .players
         .iter()
@@ -501,19 +647,46 @@ fn check_success_by_combat_exit(ctx: &LogContext) -> Option<Time> {
         .filter(|t| *t > target_exit) // ignore cases where someone dies early
         .max();
 
-    println!(
-        "last_dmg: {}, target_enter: {}, target_exit: {}, player_exit: {}",
+    log::debug!(
+        "last_dmg: {}, target_enter: {}, target_exit: {}, player_exit: {:?}",
         last_dmg,
         target_enter,
         target_exit,
-        player_exit.unwrap()
+        player_exit
     );
+
     if let Some(player_exit) = player_exit {
         if player_exit > (target_exit + 1000) && target_exit > target_enter {
+            ctx.note(
+                Topic::Success,
+                format!(
+                    "success: target left combat at {}, players only at {}",
+                    target_exit, player_exit
+                ),
+            );
             return Some(last_dmg);
         }
+        ctx.note(
+            Topic::Success,
+            format!(
+                "defeat: players left combat at {}, too close to target at {}",
+                player_exit, target_exit
+            ),
+        );
     } else if ctx.last_event > ctx.last_aware + 2000 {
+        ctx.note(
+            Topic::Success,
+            format!(
+                "success: no players left combat after target, but log continues until {} past target at {}",
+                ctx.last_event, ctx.last_aware
+            ),
+        );
         return Some(last_dmg);
+    } else {
+        ctx.note(
+            Topic::Success,
+            "defeat: no players left combat after target".into(),
+        );
     }
     None
 }
@@ -0,0 +1,342 @@
+use crate::{
+    cache::Cache,
+    error::{Error, Result},
+    retry::Retry,
+};
+
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PATH: &str = "queue.json";
+
+// work that didn't go through the first time around
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum Job {
+    Upload {
+        path: PathBuf,
+    },
+    // uploaded, but the message about it wasn't sent
+    Message {
+        path: PathBuf,
+        #[serde(default)]
+        link: Option<String>,
+    },
+}
+
+impl Job {
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Upload { path } | Self::Message { path, .. } => path,
+        }
+    }
+}
+
+// all times are unix timestamps in seconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Item {
+    pub job: Job,
+    pub added: i64,
+    pub attempts: u32,
+    pub next: i64,
+    pub error: Option<String>,
+    // failed for a reason retrying won't fix, left alone until retried by hand
+    #[serde(default)]
+    pub failed: bool,
+}
+
+impl Item {
+    // logs are named after when they started, so that's what items go by
+    pub fn id(&self) -> String {
+        self.job
+            .path()
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn matches(&self, ids: &[String]) -> bool {
+        ids.is_empty() || ids.contains(&self.id())
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.job {
+            Job::Upload { .. } => "upload",
+            Job::Message { .. } => "message",
+        };
+        let state = if self.failed { "failed" } else { "pending" };
+        write!(
+            f,
+            "{} - {} {} ({} attempts)",
+            self.id(),
+            state,
+            kind,
+            self.attempts
+        )?;
+        if let Some(error) = &self.error {
+            write!(f, ": {}", error)?;
+        }
+        write!(f, "\n    {}", self.job.path().display())
+    }
+}
+
+// pending uploads and messages, saved to disk after every change so nothing is
+// lost if snek goes down with them
+#[derive(Debug, Clone)]
+pub struct Queue {
+    cache: Arc<Mutex<Cache<String, Item>>>,
+}
+
+impl Queue {
+    pub async fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let cache = Arc::new(Mutex::new(Cache::new(path).await?));
+        Ok(Self { cache })
+    }
+
+    fn key(job: &Job) -> String {
+        job.path().display().to_string()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Cache<String, Item>)) {
+        let mut cache = self.cache.lock().unwrap();
+        f(&mut cache);
+        if let Err(e) = cache.save() {
+            log::error!("failed to save queue: {}", e);
+        }
+    }
+
+    pub fn push(&self, job: Job, error: &Error, now: i64) {
+        if error.is_transient() {
+            log::warn!("queued {} to try again later", job.path().display());
+        } else {
+            log::warn!(
+                "queued {}, it won't be tried again until `snek queue retry`",
+                job.path().display()
+            );
+        }
+        let item = Item {
+            job,
+            added: now,
+            attempts: 1,
+            next: now,
+            error: Some(error.to_string()),
+            failed: !error.is_transient(),
+        };
+        self.update(|cache| {
+            cache.insert(Self::key(&item.job), item);
+        });
+    }
+
+    // oldest first
+    pub fn items(&self) -> Vec<Item> {
+        let cache = self.cache.lock().unwrap();
+        let mut items: Vec<Item> = cache.raw().values().cloned().collect();
+        items.sort_by_key(|item| item.added);
+        items
+    }
+
+    // items that are due to be tried again at `now`
+    pub fn due(&self, now: i64) -> Vec<Item> {
+        self.items()
+            .into_iter()
+            .filter(|item| !item.failed && item.next <= now)
+            .collect()
+    }
+
+    pub fn complete(&self, job: &Job) {
+        self.update(|cache| {
+            cache.remove(&Self::key(job));
+        });
+    }
+
+    // swaps an item's job for the next step of it, i.e. once it's uploaded
+    pub fn advance(&self, job: Job) {
+        self.update(|cache| {
+            if let Some(mut item) = cache.get(&Self::key(&job)).cloned() {
+                item.job = job;
+                cache.insert(Self::key(&item.job), item);
+            }
+        });
+    }
+
+    // items that keep failing for transient reasons are given up on once
+    // they've had as many attempts as `retry` allows
+    pub fn fail(&self, job: &Job, error: &Error, retry: &Retry, now: i64) {
+        self.update(|cache| {
+            if let Some(mut item) = cache.get(&Self::key(job)).cloned() {
+                item.next = now + retry.backoff(item.attempts).as_secs() as i64;
+                item.attempts += 1;
+                item.error = Some(error.to_string());
+                item.failed = !error.is_transient();
+                if !item.failed && item.attempts >= retry.attempts {
+                    log::warn!(
+                        "giving up on {} after {} attempts",
+                        job.path().display(),
+                        item.attempts
+                    );
+                    cache.remove(&Self::key(job));
+                } else {
+                    cache.insert(Self::key(job), item);
+                }
+            }
+        });
+    }
+
+    // makes items with one of `ids` due right away, or every item if empty
+    pub fn retry(&self, ids: &[String], now: i64) -> usize {
+        let mut count = 0;
+        self.update(|cache| {
+            let items: Vec<Item> = cache
+                .raw()
+                .values()
+                .filter(|item| item.matches(ids))
+                .cloned()
+                .collect();
+            count = items.len();
+            for mut item in items {
+                item.failed = false;
+                item.next = now;
+                cache.insert(Self::key(&item.job), item);
+            }
+        });
+        count
+    }
+
+    // forgets items with one of `ids`, or every item if empty
+    pub fn remove(&self, ids: &[String]) -> usize {
+        let mut count = 0;
+        self.update(|cache| {
+            let keys: Vec<String> = cache
+                .raw()
+                .iter()
+                .filter(|(_, item)| item.matches(ids))
+                .map(|(key, _)| key.clone())
+                .collect();
+            count = keys.len();
+            for key in keys {
+                cache.remove(&key);
+            }
+        });
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("snek-{}.json", name))
+    }
+
+    // a queue nothing has been added to yet
+    async fn queue(name: &str) -> Queue {
+        let _ = std::fs::remove_file(path(name));
+        Queue::new(path(name)).await.unwrap()
+    }
+
+    fn upload(name: &str) -> Job {
+        Job::Upload {
+            path: PathBuf::from(format!("Arkk/{}.zevtc", name)),
+        }
+    }
+
+    fn transient() -> Error {
+        Error::Status(reqwest::StatusCode::SERVICE_UNAVAILABLE, None)
+    }
+
+    #[tokio::test]
+    async fn counts_attempts() {
+        let queue = queue("queue-attempts").await;
+        let retry = Retry::default();
+        let job = upload("20200408-233716");
+
+        queue.push(job.clone(), &transient(), 100);
+        assert_eq!(1, queue.due(100).len());
+        assert_eq!(1, queue.items()[0].attempts);
+
+        queue.fail(&job, &transient(), &retry, 100);
+        let item = &queue.items()[0];
+        assert_eq!((2, 104), (item.attempts, item.next));
+        assert!(queue.due(103).is_empty());
+        assert_eq!(1, queue.due(104).len());
+
+        queue.fail(&job, &transient(), &retry, 104);
+        assert_eq!((3, 112), (queue.items()[0].attempts, queue.items()[0].next));
+
+        // uploaded, the message is next and shouldn't start over
+        let message = Job::Message {
+            path: job.path().to_owned(),
+            link: Some("https://dps.report/xXXx".to_string()),
+        };
+        queue.advance(message.clone());
+        let item = &queue.items()[0];
+        assert!(matches!(item.job, Job::Message { .. }));
+        assert_eq!(3, item.attempts);
+
+        queue.complete(&message);
+        assert!(queue.items().is_empty());
+    }
+
+    #[tokio::test]
+    async fn drops_jobs_out_of_attempts() {
+        let queue = queue("queue-drops").await;
+        let retry = Retry {
+            attempts: 3,
+            ..Retry::default()
+        };
+        let job = upload("20200408-233716");
+
+        queue.push(job.clone(), &transient(), 0);
+        queue.fail(&job, &transient(), &retry, 0);
+        assert_eq!(2, queue.items()[0].attempts);
+        queue.fail(&job, &transient(), &retry, 0);
+        assert!(queue.items().is_empty());
+
+        // failing for good waits on `snek queue retry` instead
+        let job = upload("20200408-233717");
+        queue.push(job.clone(), &transient(), 0);
+        queue.fail(&job, &transient(), &retry, 0);
+        queue.fail(&job, &Error::NoRecentLog, &retry, 0);
+        let item = &queue.items()[0];
+        assert!(item.failed);
+        assert!(queue.due(i64::MAX).is_empty());
+
+        assert_eq!(1, queue.retry(&[], 50));
+        assert_eq!(1, queue.due(50).len());
+    }
+
+    #[tokio::test]
+    async fn keeps_jobs_across_reloads() {
+        let queue = queue("queue-reload").await;
+        let retry = Retry::default();
+        let first = upload("20200408-233716");
+        let second = upload("20200408-233800");
+
+        queue.push(first.clone(), &transient(), 10);
+        queue.push(second.clone(), &Error::NoRecentLog, 20);
+        queue.fail(&first, &transient(), &retry, 30);
+
+        let reloaded = Queue::new(path("queue-reload")).await.unwrap();
+        let items = reloaded.items();
+        let ids: Vec<String> = items.iter().map(Item::id).collect();
+        assert_eq!(vec!["20200408-233716", "20200408-233800"], ids);
+        assert_eq!(
+            (2, 34, false),
+            (items[0].attempts, items[0].next, items[0].failed)
+        );
+        assert_eq!((1, true), (items[1].attempts, items[1].failed));
+        assert_eq!(Some(transient().to_string()), items[0].error);
+
+        assert_eq!(1, queue.remove(&["20200408-233800".to_string()]));
+        let reloaded = Queue::new(path("queue-reload")).await.unwrap();
+        assert_eq!(1, reloaded.items().len());
+    }
+}
@@ -0,0 +1,239 @@
+use crate::{
+    parse::Time,
+    session::{self, Pull, DAY},
+    target::Target,
+};
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::NaiveDate;
+
+// how many of the most died-to mechanics are listed
+const MECHANICS: usize = 5;
+
+// aggregated pulls of every day from `since` up to and including `until`
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub since: NaiveDate,
+    pub until: NaiveDate,
+    pub attempts: usize,
+    pub bosses: Vec<BossReport>,
+    pub mechanics: Vec<Mechanic>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BossReport {
+    pub target: Target,
+    pub kills: usize,
+    pub wipes: usize,
+    pub fastest: Option<Time>,
+    pub fastest_link: Option<String>,
+    pub average: Option<Time>,
+    // average kill time over the same amount of days before `since`
+    pub previous_average: Option<Time>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mechanic {
+    pub name: String,
+    pub deaths: usize,
+}
+
+impl Report {
+    // `pulls` need to go back as far as the same amount of days before `since`
+    // for the previous averages
+    pub fn new(pulls: &[Pull], since: NaiveDate, until: NaiveDate) -> Self {
+        let (start, end) = bounds(since, until);
+        let current = between(pulls, start, end);
+        let previous = between(pulls, start - (end - start), start);
+
+        let mut bosses: BTreeMap<Target, BossReport> = BTreeMap::new();
+        for record in current.iter().map(|pull| &pull.record) {
+            for encounter in &record.encounters {
+                let boss = bosses
+                    .entry(encounter.target)
+                    .or_insert_with(|| BossReport::new(encounter.target));
+                if !encounter.success {
+                    boss.wipes += 1;
+                    continue;
+                }
+
+                boss.kills += 1;
+                if boss
+                    .fastest
+                    .is_none_or(|fastest| encounter.duration < fastest)
+                {
+                    boss.fastest = Some(encounter.duration);
+                    boss.fastest_link = record.link.clone();
+                }
+            }
+        }
+
+        for boss in bosses.values_mut() {
+            boss.average = average_kill(&current, boss.target);
+            boss.previous_average = average_kill(&previous, boss.target);
+        }
+
+        let mut deaths: HashMap<&str, usize> = HashMap::new();
+        for death in current.iter().flat_map(|pull| &pull.record.deaths) {
+            let name = death.skill.as_deref().unwrap_or("unknown");
+            *deaths.entry(name).or_default() += 1;
+        }
+        let mut mechanics: Vec<Mechanic> = deaths
+            .into_iter()
+            .map(|(name, deaths)| Mechanic {
+                name: name.to_string(),
+                deaths,
+            })
+            .collect();
+        mechanics.sort_by(|a, b| b.deaths.cmp(&a.deaths).then_with(|| a.name.cmp(&b.name)));
+        mechanics.truncate(MECHANICS);
+
+        Self {
+            since,
+            until,
+            attempts: current.len(),
+            bosses: bosses.into_values().collect(),
+            mechanics,
+        }
+    }
+}
+
+impl BossReport {
+    const fn new(target: Target) -> Self {
+        Self {
+            target,
+            kills: 0,
+            wipes: 0,
+            fastest: None,
+            fastest_link: None,
+            average: None,
+            previous_average: None,
+        }
+    }
+}
+
+// unix milliseconds from the reset on `since` to the one after `until`
+fn bounds(since: NaiveDate, until: NaiveDate) -> (i64, i64) {
+    (
+        session::reset_start(since),
+        session::reset_start(until) + DAY,
+    )
+}
+
+// unix timestamp in milliseconds of the earliest pull a report needs
+pub fn earliest(since: NaiveDate, until: NaiveDate) -> i64 {
+    let (start, end) = bounds(since, until);
+    start - (end - start)
+}
+
+// pulls that started between `start` and `end` in unix milliseconds
+fn between(pulls: &[Pull], start: i64, end: i64) -> Vec<&Pull> {
+    pulls
+        .iter()
+        .filter(|pull| (start..end).contains(&pull.start))
+        .collect()
+}
+
+fn average_kill(pulls: &[&Pull], target: Target) -> Option<Time> {
+    let kills: Vec<Time> = pulls
+        .iter()
+        .flat_map(|pull| &pull.record.encounters)
+        .filter(|encounter| encounter.target == target && encounter.success)
+        .map(|encounter| encounter.duration)
+        .collect();
+
+    if kills.is_empty() {
+        None
+    } else {
+        Some(kills.iter().sum::<Time>() / kills.len() as Time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::Log;
+
+    const MINUTE: i64 = 60 * 1000;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, 9, day).unwrap()
+    }
+
+    // a pull of `target` started an hour after reset on the `day`th
+    fn pull(target: Target, day: u32, minutes: i64, duration: Time, death: Option<&str>) -> Pull {
+        let start = session::reset_start(date(day)) + 60 * MINUTE + minutes * MINUTE;
+        let log = Log::new(target, format!("{:?}/{}.zevtc", target, start));
+        let deaths = death.map_or_else(Vec::new, |skill| {
+            vec![serde_json::json!({"player": "a", "skill": skill, "time": 0})]
+        });
+        let record = serde_json::from_value(serde_json::json!({
+            "target": target,
+            "file": log.file_name(),
+            "link": format!("https://dps.report/{}", start),
+            "encounters": [{
+                "target": target,
+                "success": death.is_none(),
+                "duration": duration,
+            }],
+            "deaths": deaths,
+        }))
+        .unwrap();
+        Pull::new(log, Vec::new(), record, start)
+    }
+
+    #[test]
+    fn reports_kills_and_wipes() {
+        let pulls = vec![
+            pull(Target::Skor, 14, 0, 60_000, Some("Crystal Bomb")),
+            pull(Target::Skor, 14, 5, 70_000, None),
+            pull(Target::Skor, 15, 0, 50_000, None),
+            pull(Target::Skor, 15, 5, 10_000, Some("Crystal Bomb")),
+            pull(Target::Arts, 15, 10, 20_000, Some("Obliterate")),
+            pull(Target::Arts, 15, 15, 90_000, None),
+            // outside of the report, but what it's compared against
+            pull(Target::Skor, 13, 0, 80_000, None),
+            pull(Target::Skor, 12, 0, 100_000, None),
+            pull(Target::Skor, 11, 0, 10_000, None),
+            pull(Target::Arts, 16, 0, 10_000, None),
+        ];
+
+        let report = Report::new(&pulls, date(14), date(15));
+        assert_eq!(6, report.attempts);
+
+        let skor = &report.bosses[0];
+        assert_eq!(Target::Skor, skor.target);
+        assert_eq!((2, 2), (skor.kills, skor.wipes));
+        assert_eq!(Some(50_000), skor.fastest);
+        let fastest_start = session::reset_start(date(15)) + 60 * MINUTE;
+        assert_eq!(
+            Some(format!("https://dps.report/{}", fastest_start)),
+            skor.fastest_link
+        );
+        assert_eq!(Some(60_000), skor.average);
+        assert_eq!(Some(90_000), skor.previous_average);
+
+        let arts = &report.bosses[1];
+        assert_eq!(Target::Arts, arts.target);
+        assert_eq!((1, 1), (arts.kills, arts.wipes));
+        assert_eq!(Some(90_000), arts.average);
+        assert_eq!(None, arts.previous_average);
+
+        let mechanics: Vec<(&str, usize)> = report
+            .mechanics
+            .iter()
+            .map(|mechanic| (mechanic.name.as_str(), mechanic.deaths))
+            .collect();
+        assert_eq!(vec![("Crystal Bomb", 2), ("Obliterate", 1)], mechanics);
+    }
+
+    #[test]
+    fn reports_nothing() {
+        let report = Report::new(&[], date(14), date(15));
+        assert_eq!(0, report.attempts);
+        assert!(report.bosses.is_empty());
+        assert!(report.mechanics.is_empty());
+        assert_eq!(session::reset_start(date(12)), earliest(date(14), date(15)));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::error::{Error, Result};
+
+use std::{future::Future, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+// how often and how patiently requests that failed for a transient reason are
+// tried again, delays are in seconds
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Retry {
+    // including the first try
+    pub attempts: u32,
+    pub delay: u64,
+    pub max_delay: u64,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            attempts: 5,
+            delay: 2,
+            max_delay: 120,
+        }
+    }
+}
+
+impl Retry {
+    // doubles with every attempt
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self.delay.saturating_mul(1_u64 << attempt.min(16));
+        Duration::from_secs(delay.min(self.max_delay))
+    }
+
+    // unless the server asked for something else, which is capped all the same
+    fn delay(&self, attempt: u32, error: &Error) -> Duration {
+        error
+            .retry_after()
+            .map(|delay| delay.min(Duration::from_secs(self.max_delay)))
+            .unwrap_or_else(|| self.backoff(attempt))
+    }
+
+    // runs `f` until it succeeds, fails permanently or runs out of attempts
+    pub async fn run<F, Fut, T>(&self, what: &str, mut f: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match f().await {
+                Ok(t) => return Ok(t),
+                Err(e) if e.is_transient() && attempt + 1 < self.attempts => {
+                    let delay = self.delay(attempt, &e);
+                    log::warn!(
+                        "{} failed: {}, retrying in {}s ({}/{})",
+                        what,
+                        e,
+                        delay.as_secs(),
+                        attempt + 1,
+                        self.attempts - 1
+                    );
+                    tokio::time::delay_for(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+// turns an unsuccessful response into an error, keeping how long the server
+// wants to be left alone for
+pub(crate) fn check(res: reqwest::Response) -> Result<reqwest::Response> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
+    }
+
+    let retry_after = res
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after);
+    Err(Error::Status(status, retry_after))
+}
+
+// either a number of seconds or a date, i.e. Wed, 21 Oct 2015 07:28:00 GMT
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(secs) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    let secs = date.timestamp() - chrono::Utc::now().timestamp();
+    Some(Duration::from_secs(secs.max(0) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off() {
+        let retry = Retry::default();
+        let delays: Vec<u64> = (0..9)
+            .map(|attempt| retry.backoff(attempt).as_secs())
+            .collect();
+        assert_eq!(vec![2, 4, 8, 16, 32, 64, 120, 120, 120], delays);
+        assert_eq!(Duration::from_secs(120), retry.backoff(u32::MAX));
+
+        let retry = Retry {
+            delay: u64::MAX / 2,
+            max_delay: u64::MAX,
+            ..Retry::default()
+        };
+        assert_eq!(Duration::from_secs(u64::MAX), retry.backoff(3));
+    }
+
+    #[test]
+    fn waits_as_long_as_asked() {
+        let retry = Retry::default();
+        let status = reqwest::StatusCode::TOO_MANY_REQUESTS;
+        let asked = Error::Status(status, Some(Duration::from_secs(7)));
+        assert_eq!(Duration::from_secs(7), retry.delay(4, &asked));
+        let not_asked = Error::Status(status, None);
+        assert_eq!(Duration::from_secs(32), retry.delay(4, &not_asked));
+
+        // `max_delay` at most, however long the server asks for
+        let too_long = Error::Status(status, Some(Duration::from_secs(86400)));
+        assert_eq!(Duration::from_secs(120), retry.delay(0, &too_long));
+        let tomorrow = chrono::Utc::now() + chrono::Duration::days(1);
+        let too_long = Error::Status(status, parse_retry_after(&tomorrow.to_rfc2822()));
+        assert_eq!(Duration::from_secs(120), retry.delay(0, &too_long));
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        assert_eq!(Some(Duration::from_secs(120)), parse_retry_after("120"));
+        assert_eq!(Some(Duration::from_secs(0)), parse_retry_after(" 0 "));
+        assert_eq!(None, parse_retry_after("soon"));
+        assert_eq!(None, parse_retry_after("-1"));
+
+        let in_a_minute = chrono::Utc::now() + chrono::Duration::seconds(60);
+        let secs = parse_retry_after(&in_a_minute.to_rfc2822())
+            .unwrap()
+            .as_secs();
+        assert!((58..=60).contains(&secs), "{}", secs);
+        let in_a_minute = in_a_minute.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        let secs = parse_retry_after(&in_a_minute).unwrap().as_secs();
+        assert!((58..=60).contains(&secs), "{}", secs);
+
+        // already over
+        assert_eq!(
+            Some(Duration::from_secs(0)),
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+    }
+
+    #[tokio::test]
+    async fn gives_up() {
+        let retry = Retry {
+            attempts: 3,
+            delay: 0,
+            max_delay: 0,
+        };
+
+        let mut tries = 0;
+        let res: Result<()> = retry
+            .run("test", || {
+                tries += 1;
+                let status = reqwest::StatusCode::SERVICE_UNAVAILABLE;
+                async move { Err(Error::Status(status, None)) }
+            })
+            .await;
+        assert!(res.is_err());
+        assert_eq!(3, tries);
+
+        // not worth trying again
+        let mut tries = 0;
+        let res: Result<()> = retry
+            .run("test", || {
+                tries += 1;
+                async { Err(Error::NoRecentLog) }
+            })
+            .await;
+        assert!(res.is_err());
+        assert_eq!(1, tries);
+    }
+}
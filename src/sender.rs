@@ -1,22 +1,132 @@
-use crate::error::Result;
+use crate::{
+    core::LogInfo,
+    error::{Error, Result},
+    filter::Only,
+    message::{
+        AllowedMentions, Generator, Mentions, SessionGenerator, Style, TextGenerator,
+        WebhookGenerator,
+    },
+    retry::{self, Retry},
+    session::Session,
+    sink::Sink,
+    template::Templates,
+    upload,
+};
 
-use std::fmt::Display;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
 use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 
 use async_trait::async_trait;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::time::Instant;
 
 #[async_trait]
-pub trait Sender {
+pub trait Sender: Send {
     async fn send<M>(&mut self, msg: M) -> Result<()>
     where
         M: Display + Serialize + Send + Sync;
+
+    // senders that can't attach files just send the message
+    async fn send_with_files<M>(&mut self, msg: M, files: &[PathBuf]) -> Result<()>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        let _ = files;
+        self.send(msg).await
+    }
+
+    // sends what `msg_gen` makes of `logs`, senders that decide for themselves
+    // which logs they want & how they look make their own
+    async fn send_logs<G>(
+        &mut self,
+        msg_gen: &G,
+        logs: &[LogInfo<'_>],
+        files: &[PathBuf],
+    ) -> Result<()>
+    where
+        G: Generator + Sync,
+        G::Message: Send + Sync,
+    {
+        let msg = msg_gen.generate(logs);
+        self.send_with_files(msg, files).await
+    }
+
+    // edits the message with `id` if there is one & the sender can, otherwise
+    // sends a new one, returning its id if the sender keeps track of them
+    async fn send_or_edit<M>(&mut self, msg: M, id: Option<&str>) -> Result<Option<String>>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        let _ = id;
+        self.send(msg).await?;
+        Ok(None)
+    }
+
+    // whether `send_or_edit` actually edits, anything else would post the
+    // whole session again for every log in live mode
+    fn can_edit(&self) -> bool {
+        false
+    }
+
+    // keeps the message with `id` about `session` up to date with `logs`, or
+    // sends it if there isn't one yet. senders that can't edit get `new`, the
+    // logs that just came in, as if live mode was off
+    async fn send_live<G>(
+        &mut self,
+        msg_gen: &G,
+        session: &Session,
+        logs: &[LogInfo<'_>],
+        new: &[LogInfo<'_>],
+        files: &[PathBuf],
+        id: Option<&str>,
+    ) -> Result<Option<String>>
+    where
+        G: SessionGenerator + Sync,
+        G::Message: Send + Sync,
+    {
+        if !self.can_edit() {
+            if !new.is_empty() {
+                self.send_logs(msg_gen, new, files).await?;
+            }
+            return Ok(None);
+        }
+        let msg = msg_gen.generate_live(session, logs);
+        self.send_or_edit(msg, id).await
+    }
 }
 
+// discord's limits on a single message, see
+// https://discord.com/developers/docs/resources/channel#embed-object-embed-limits
+const MAX_EMBEDS: usize = 10;
+const MAX_EMBED_CHARS: usize = 6000;
+const MAX_DESCRIPTION_CHARS: usize = 4096;
+const MAX_CONTENT_CHARS: usize = 2000;
+
 #[derive(Debug)]
 pub struct Webhook {
     url: String,
     client: reqwest::Client,
+    retry: Retry,
+    // when discord said the webhook can be used again, if it's been used up
+    ready_at: Option<Instant>,
+    // posts into an existing thread or forum post
+    thread_id: Option<String>,
+    // starts a new forum post for each message, named this
+    thread_name: Option<String>,
+    allowed_mentions: Option<AllowedMentions>,
+}
+
+// a message discord posted, it only answers with it when asked to wait
+#[derive(Deserialize)]
+struct Posted {
+    id: String,
+    // the thread it went in, if it went in one
+    channel_id: String,
 }
 
 impl Webhook {
@@ -25,12 +135,159 @@ impl Webhook {
         Self {
             url: url.to_string(),
             client,
+            retry: Retry::default(),
+            ready_at: None,
+            thread_id: None,
+            thread_name: None,
+            allowed_mentions: None,
         }
     }
 
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub const fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_thread_id(mut self, thread_id: Option<String>) -> Self {
+        self.thread_id = thread_id;
+        self
+    }
+
+    pub fn with_thread_name(mut self, thread_name: Option<String>) -> Self {
+        self.thread_name = thread_name;
+        self
+    }
+
+    pub fn with_allowed_mentions(mut self, allowed_mentions: Option<AllowedMentions>) -> Self {
+        self.allowed_mentions = allowed_mentions;
+        self
+    }
+
+    // i.e. https://discord.com/api/webhooks/<id>/<token>, optionally with an
+    // api version after `api`
     pub fn validate_url(url: &str) -> bool {
-        const REFERENCE_URL: &str = "https://discordapp.com/api/webhooks/ABCDEFGHIJKLMNOPQR/ABCDEFGHIJKLMNOPQRSTUVWXYZ01234567890ABCDEFGHIJKLMNOPQRSTUVWXYZ01234";
-        url.len() == REFERENCE_URL.len() && url[0..35] == REFERENCE_URL[0..35]
+        const HOSTS: &[&str] = &[
+            "discord.com",
+            "discordapp.com",
+            "canary.discord.com",
+            "ptb.discord.com",
+        ];
+        let segments = match https_segments(url, HOSTS) {
+            Some(segments) => segments,
+            None => return false,
+        };
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        let segments = match segments.as_slice() {
+            ["api", version, rest @ ..] if version.starts_with('v') => rest,
+            ["api", rest @ ..] => rest,
+            _ => return false,
+        };
+        matches!(segments, ["webhooks", id, token] if is_token(id) && is_token(token))
+    }
+
+    fn prepare(&self, message: &mut Value) {
+        if let (Some(allowed_mentions), Value::Object(message)) = (&self.allowed_mentions, message)
+        {
+            if let Ok(allowed_mentions) = serde_json::to_value(allowed_mentions) {
+                message.insert("allowed_mentions".to_string(), allowed_mentions);
+            }
+        }
+    }
+
+    // posts into `thread` if given, otherwise into a new forum post if the
+    // webhook starts those
+    async fn post(
+        &mut self,
+        mut message: Value,
+        files: &[PathBuf],
+        thread: Option<&str>,
+    ) -> Result<Posted> {
+        self.prepare(&mut message);
+        if let (None, Some(name), Value::Object(message)) =
+            (thread, &self.thread_name, &mut message)
+        {
+            message.insert("thread_name".to_string(), Value::String(name.clone()));
+        }
+
+        let mut query = vec![("wait", "true")];
+        query.extend(thread.map(|thread| ("thread_id", thread)));
+        let url = self.url.clone();
+        let res = self
+            .request(reqwest::Method::POST, &url, &query, &message, files)
+            .await?;
+        Ok(res.json().await?)
+    }
+
+    async fn edit(&mut self, mut message: Value, id: &str, thread: Option<&str>) -> Result<()> {
+        self.prepare(&mut message);
+        let query: Vec<_> = thread
+            .map(|thread| ("thread_id", thread))
+            .into_iter()
+            .collect();
+        let url = format!("{}/messages/{}", self.url, id);
+        self.request(reqwest::Method::PATCH, &url, &query, &message, &[])
+            .await?;
+        Ok(())
+    }
+
+    async fn request(
+        &mut self,
+        method: reqwest::Method,
+        url: &str,
+        query: &[(&str, &str)],
+        message: &Value,
+        files: &[PathBuf],
+    ) -> Result<reqwest::Response> {
+        let retry = self.retry;
+        let res = retry
+            .run("posting to discord webhook", || {
+                self.try_request(method.clone(), url, query, message, files)
+            })
+            .await?;
+        self.ready_at = reset_after(res.headers()).map(|delay| Instant::now() + delay);
+        Ok(res)
+    }
+
+    async fn try_request(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        query: &[(&str, &str)],
+        message: &Value,
+        files: &[PathBuf],
+    ) -> Result<reqwest::Response> {
+        if let Some(ready_at) = self.ready_at {
+            tokio::time::delay_until(ready_at).await;
+        }
+
+        let req = self.client.request(method, url).query(query);
+        let req = if files.is_empty() {
+            req.json(message)
+        } else {
+            let mut form = reqwest::multipart::Form::new()
+                .text("payload_json", serde_json::to_string(message)?);
+            for (idx, path) in files.iter().enumerate() {
+                form = form.part(format!("files[{}]", idx), upload::file_part(path).await?);
+            }
+            req.multipart(form)
+        };
+
+        let res = req.send().await?;
+        if res.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(rate_limited(res).await);
+        }
+        // discord says what was wrong with the message
+        if res.status().is_client_error() {
+            let status = res.status();
+            log::warn!("discord rejected message: {}", res.text().await?);
+            return Err(Error::Status(status, None));
+        }
+        retry::check(res)
     }
 }
 
@@ -40,10 +297,933 @@ impl Sender for Webhook {
     where
         M: Display + Serialize + Send + Sync,
     {
-        log::info!("posting log to discord webhook");
-        self.client.post(&self.url).json(&msg).send().await?;
+        self.send_with_files(msg, &[]).await
+    }
+
+    async fn send_with_files<M>(&mut self, msg: M, files: &[PathBuf]) -> Result<()>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        let messages = split(serde_json::to_value(&msg)?);
+        if messages.len() > 1 {
+            log::info!(
+                "message is too big for discord, splitting it into {}",
+                messages.len()
+            );
+        }
+
+        if files.is_empty() {
+            log::info!("posting log to discord webhook");
+        } else {
+            log::info!("posting log to discord webhook with {} files", files.len());
+        }
+        let mut thread = self.thread_id.clone();
+        for (idx, message) in messages.into_iter().enumerate() {
+            // files go along with the first message
+            let files = if idx == 0 { files } else { &[] };
+            let posted = self.post(message, files, thread.as_deref()).await?;
+            // the rest of the message follows the first into its forum post
+            if self.thread_name.is_some() {
+                thread.get_or_insert(posted.channel_id);
+            }
+        }
+        Ok(())
+    }
+
+    // ids of messages that started a forum post are stored as
+    // `<thread id>/<message id>`, they can't be edited without both
+    async fn send_or_edit<M>(&mut self, msg: M, id: Option<&str>) -> Result<Option<String>>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        // there's only one message to edit, so whatever doesn't fit is lost
+        let mut messages = split(serde_json::to_value(&msg)?);
+        if messages.len() > 1 {
+            log::warn!("message is too big for discord, only the first part is kept");
+        }
+        let message = messages.swap_remove(0);
+
+        if let Some(id) = id {
+            let (thread, message_id) = match id.split_once('/') {
+                Some((thread, message_id)) => (Some(thread.to_string()), message_id),
+                None => (self.thread_id.clone(), id),
+            };
+            log::info!("editing discord message {}", message_id);
+            match self
+                .edit(message.clone(), message_id, thread.as_deref())
+                .await
+            {
+                Ok(()) => return Ok(Some(id.to_string())),
+                Err(Error::Status(reqwest::StatusCode::NOT_FOUND, _)) => {
+                    log::info!("discord message {} is gone, posting a new one", message_id);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        log::info!("posting live message to discord webhook");
+        let thread = self.thread_id.clone();
+        let posted = self.post(message, &[], thread.as_deref()).await?;
+        if thread.is_none() && self.thread_name.is_some() {
+            return Ok(Some(format!("{}/{}", posted.channel_id, posted.id)));
+        }
+        Ok(Some(posted.id))
+    }
+
+    fn can_edit(&self) -> bool {
+        true
+    }
+}
+
+// how long discord wants to be left alone for after a 429, the body is more
+// precise than the headers
+async fn rate_limited(res: reqwest::Response) -> Error {
+    #[derive(Deserialize)]
+    struct RateLimit {
+        retry_after: f64,
+    }
+
+    let status = res.status();
+    let header = header_secs(res.headers(), "retry-after")
+        .or_else(|| header_secs(res.headers(), "x-ratelimit-reset-after"));
+    let body = res
+        .json::<RateLimit>()
+        .await
+        .ok()
+        .and_then(|limit| secs(limit.retry_after));
+    log::warn!("rate limited by discord");
+    Error::Status(status, body.or(header))
+}
+
+// once the last request a webhook has left is used up, how long until it resets
+fn reset_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let remaining = headers.get("x-ratelimit-remaining")?.to_str().ok()?;
+    if remaining.trim() != "0" {
+        return None;
+    }
+    header_secs(headers, "x-ratelimit-reset-after")
+}
+
+// i.e. 1.234
+fn header_secs(headers: &reqwest::header::HeaderMap, name: &str) -> Option<Duration> {
+    secs(headers.get(name)?.to_str().ok()?.trim().parse().ok()?)
+}
+
+fn secs(secs: f64) -> Option<Duration> {
+    (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+}
+
+// breaks a message up into as many as it takes to fit within discord's limits,
+// keeping everything but the content and embeds the same in each
+fn split(message: Value) -> Vec<Value> {
+    let mut message = match message {
+        Value::Object(message) => message,
+        message => return vec![message],
+    };
+    let content = match message.remove("content") {
+        Some(Value::String(content)) => content,
+        _ => String::new(),
+    };
+    let embeds = match message.remove("embeds") {
+        Some(Value::Array(embeds)) => Some(embeds),
+        _ => None,
+    };
+    let has_embeds = embeds.is_some();
+
+    // (content, embeds, characters in embeds)
+    let mut parts: Vec<(String, Vec<Value>, usize)> = split_text(&content, MAX_CONTENT_CHARS)
+        .into_iter()
+        .map(|content| (content, Vec::new(), 0))
+        .collect();
+    for mut embed in embeds.unwrap_or_default() {
+        truncate_embed(&mut embed);
+        let chars = embed_chars(&embed);
+        let last = parts.last_mut().unwrap();
+        if last.1.len() < MAX_EMBEDS && last.2 + chars <= MAX_EMBED_CHARS {
+            last.1.push(embed);
+            last.2 += chars;
+        } else {
+            parts.push((String::new(), vec![embed], chars));
+        }
+    }
+
+    parts
+        .into_iter()
+        .map(|(content, embeds, _)| {
+            let mut part = message.clone();
+            part.insert("content".to_string(), Value::String(content));
+            if has_embeds {
+                part.insert("embeds".to_string(), Value::Array(embeds));
+            }
+            Value::Object(part)
+        })
+        .collect()
+}
+
+// breaks at line ends where possible, always returns at least one part
+fn split_text(text: &str, max: usize) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    for line in text.split_inclusive('\n') {
+        let mut line = line;
+        while !line.is_empty() {
+            let last = parts.last_mut().unwrap();
+            let room = max - last.chars().count();
+            let len = line.chars().count();
+            if len <= room {
+                last.push_str(line);
+                break;
+            }
+            if !last.is_empty() && len <= max {
+                parts.push(String::new());
+                continue;
+            }
+            // too long for a message of its own, cut it wherever it ends up
+            let at = line
+                .char_indices()
+                .nth(room)
+                .map_or(line.len(), |(idx, _)| idx);
+            last.push_str(&line[..at]);
+            line = &line[at..];
+            parts.push(String::new());
+        }
+    }
+    if parts.len() > 1 && parts.last().is_some_and(String::is_empty) {
+        parts.pop();
+    }
+    parts
+}
+
+// an embed that's too big on its own loses the end of its description
+fn truncate_embed(embed: &mut Value) {
+    let chars = embed_chars(embed);
+    let description = match embed.get_mut("description") {
+        Some(Value::String(description)) => description,
+        _ => return,
+    };
+    let len = description.chars().count();
+    let over = len
+        .saturating_sub(MAX_DESCRIPTION_CHARS)
+        .max(chars.saturating_sub(MAX_EMBED_CHARS));
+    if over > 0 {
+        let keep = len.saturating_sub(over + 1);
+        *description = description.chars().take(keep).collect::<String>() + "…";
+    }
+}
+
+// what counts towards the character limit of an embed
+fn embed_chars(embed: &Value) -> usize {
+    let chars = |value: Option<&Value>| {
+        value
+            .and_then(Value::as_str)
+            .map_or(0, |s| s.chars().count())
+    };
+    let fields = embed
+        .get("fields")
+        .and_then(Value::as_array)
+        .map_or(0, |fields| {
+            fields
+                .iter()
+                .map(|field| chars(field.get("name")) + chars(field.get("value")))
+                .sum()
+        });
+    chars(embed.get("title"))
+        + chars(embed.get("description"))
+        + chars(embed.pointer("/footer/text"))
+        + chars(embed.pointer("/author/name"))
+        + fields
+}
+
+// the path segments of an https url on one of `hosts`, without empty ones
+fn https_segments(url: &str, hosts: &[&str]) -> Option<Vec<String>> {
+    let url = url::Url::parse(url).ok()?;
+    if url.scheme() != "https" || !hosts.contains(&url.host_str()?) {
+        return None;
+    }
+    let segments = url.path_segments()?;
+    Some(
+        segments
+            .filter(|s| !s.is_empty())
+            .map(str::to_owned)
+            .collect(),
+    )
+}
+
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// what's on the other end of a webhook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    #[default]
+    Discord,
+    // an incoming webhook
+    Slack,
+    // a room on a homeserver, posted to with a user's access token
+    Matrix,
+    // anything else that takes a JSON POST
+    Json,
+    // files that keep a record of every log, the url being where they are
+    Jsonl,
+    Csv,
+    Markdown,
+    Html,
+}
+
+impl Kind {
+    pub fn is_discord(&self) -> bool {
+        *self == Self::Discord
+    }
+
+    pub const fn is_file(self) -> bool {
+        matches!(self, Self::Jsonl | Self::Csv | Self::Markdown | Self::Html)
+    }
+
+    // what live mode needs, files get a row per log instead
+    pub const fn can_edit(self) -> bool {
+        matches!(self, Self::Discord | Self::Matrix)
+    }
+
+    pub fn validate_url(self, url: &str) -> bool {
+        match self {
+            Self::Discord => Webhook::validate_url(url),
+            Self::Slack => Slack::validate_url(url),
+            Self::Matrix | Self::Json => url::Url::parse(url).is_ok_and(|url| {
+                matches!(url.scheme(), "http" | "https") && url.host_str().is_some()
+            }),
+            Self::Jsonl | Self::Csv | Self::Markdown | Self::Html => !url.trim().is_empty(),
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "discord" => Ok(Self::Discord),
+            "slack" => Ok(Self::Slack),
+            "matrix" => Ok(Self::Matrix),
+            "json" => Ok(Self::Json),
+            "jsonl" => Ok(Self::Jsonl),
+            "csv" => Ok(Self::Csv),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            _ => Err(format!(
+                "unknown webhook kind `{}`, expected discord, slack, matrix, json, jsonl, csv, markdown or html",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Discord => write!(f, "discord"),
+            Self::Slack => write!(f, "slack"),
+            Self::Matrix => write!(f, "matrix"),
+            Self::Json => write!(f, "json"),
+            Self::Jsonl => write!(f, "jsonl"),
+            Self::Csv => write!(f, "csv"),
+            Self::Markdown => write!(f, "markdown"),
+            Self::Html => write!(f, "html"),
+        }
+    }
+}
+
+// posts messages as plain text, slack doesn't understand discord's embeds
+#[derive(Debug)]
+pub struct Slack {
+    url: String,
+    client: reqwest::Client,
+    retry: Retry,
+}
+
+impl Slack {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+            retry: Retry::default(),
+        }
+    }
+
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub const fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    // i.e. https://hooks.slack.com/services/T0000/B0000/XXXXXXXX
+    pub fn validate_url(url: &str) -> bool {
+        match https_segments(url, &["hooks.slack.com"]) {
+            Some(segments) => matches!(
+                segments.iter().map(String::as_str).collect::<Vec<_>>().as_slice(),
+                ["services", team, hook, token] if is_token(team) && is_token(hook) && is_token(token)
+            ),
+            None => false,
+        }
+    }
+}
+
+#[async_trait]
+impl Sender for Slack {
+    async fn send<M>(&mut self, msg: M) -> Result<()>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        let message = serde_json::json!({ "text": msg.to_string().trim() });
+        log::info!("posting log to slack webhook");
+        let (client, url) = (&self.client, &self.url);
+        self.retry
+            .run("posting to slack webhook", || async {
+                let res = client.post(url).json(&message).send().await?;
+                rejected(res, "slack").await
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+// posts messages as plain text to a room, as whoever the access token
+// belongs to
+#[derive(Debug)]
+pub struct Matrix {
+    // of the homeserver, i.e. https://matrix.org
+    url: String,
+    // i.e. !abcdefg:matrix.org
+    room: String,
+    access_token: String,
+    client: reqwest::Client,
+    retry: Retry,
+    // messages are sent at most once per transaction id, so each needs its own
+    txn_prefix: i64,
+    txn: u64,
+}
+
+#[derive(Deserialize)]
+struct Event {
+    event_id: String,
+}
+
+impl Matrix {
+    pub fn new(url: &str, room: &str, access_token: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            room: room.to_string(),
+            access_token: access_token.to_string(),
+            client: reqwest::Client::new(),
+            retry: Retry::default(),
+            txn_prefix: chrono::Utc::now().timestamp_millis(),
+            txn: 0,
+        }
+    }
+
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub const fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    // room ids, not aliases, i.e. !abcdefg:matrix.org
+    pub fn validate_room(room: &str) -> bool {
+        room.starts_with('!') && room.contains(':')
+    }
+
+    async fn put(&mut self, content: Value) -> Result<String> {
+        self.txn += 1;
+        let mut url =
+            url::Url::parse(&self.url).map_err(|_| Error::InvalidWebhookUrl(self.url.clone()))?;
+        url.path_segments_mut()
+            .map_err(|_| Error::InvalidWebhookUrl(self.url.clone()))?
+            .pop_if_empty()
+            .extend(&[
+                "_matrix",
+                "client",
+                "v3",
+                "rooms",
+                &self.room,
+                "send",
+                "m.room.message",
+            ])
+            .push(&format!("snek{}.{}", self.txn_prefix, self.txn));
+
+        let (client, token) = (&self.client, &self.access_token);
+        let res = self
+            .retry
+            .run("posting to matrix room", || async {
+                let res = client
+                    .put(url.clone())
+                    .bearer_auth(token)
+                    .json(&content)
+                    .send()
+                    .await?;
+                if res.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    return Err(matrix_rate_limited(res).await);
+                }
+                rejected(res, "matrix").await
+            })
+            .await?;
+        let event: Event = res.json().await?;
+        Ok(event.event_id)
+    }
+}
+
+#[async_trait]
+impl Sender for Matrix {
+    async fn send<M>(&mut self, msg: M) -> Result<()>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        log::info!("posting log to matrix room {}", self.room);
+        let content = serde_json::json!({ "msgtype": "m.notice", "body": msg.to_string().trim() });
+        self.put(content).await?;
         Ok(())
     }
+
+    // edits replace the original event's content, which keeps its id
+    async fn send_or_edit<M>(&mut self, msg: M, id: Option<&str>) -> Result<Option<String>>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        let body = msg.to_string().trim().to_string();
+        let new_content = serde_json::json!({ "msgtype": "m.notice", "body": body });
+        match id {
+            Some(id) => {
+                log::info!("editing matrix message {}", id);
+                let content = serde_json::json!({
+                    "msgtype": "m.notice",
+                    "body": format!("* {}", body),
+                    "m.new_content": new_content,
+                    "m.relates_to": { "rel_type": "m.replace", "event_id": id },
+                });
+                self.put(content).await?;
+                Ok(Some(id.to_string()))
+            }
+            None => {
+                log::info!("posting live message to matrix room {}", self.room);
+                Ok(Some(self.put(new_content).await?))
+            }
+        }
+    }
+
+    fn can_edit(&self) -> bool {
+        true
+    }
+}
+
+// matrix says how long to wait in milliseconds
+async fn matrix_rate_limited(res: reqwest::Response) -> Error {
+    #[derive(Deserialize)]
+    struct RateLimit {
+        retry_after_ms: u64,
+    }
+
+    let status = res.status();
+    let delay = res
+        .json::<RateLimit>()
+        .await
+        .ok()
+        .map(|limit| Duration::from_millis(limit.retry_after_ms));
+    log::warn!("rate limited by matrix");
+    Error::Status(status, delay)
+}
+
+// posts `template` with `{text}` replaced by the message as text & `{message}`
+// by the message as JSON, or just the message as JSON without one
+#[derive(Debug)]
+pub struct Json {
+    url: String,
+    template: Option<String>,
+    client: reqwest::Client,
+    retry: Retry,
+}
+
+impl Json {
+    pub fn new(url: &str, template: Option<String>) -> Self {
+        Self {
+            url: url.to_string(),
+            template,
+            client: reqwest::Client::new(),
+            retry: Retry::default(),
+        }
+    }
+
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub const fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    // whether `template` comes out as JSON once filled in
+    pub fn validate_template(template: &str) -> bool {
+        render(
+            template,
+            "text \"quoted\"\n",
+            &serde_json::json!({ "content": "" }),
+        )
+        .is_ok()
+    }
+}
+
+// `text` goes in as the inside of a JSON string, so templates put quotes
+// around it, i.e. {"body": "{text}"}
+fn render(template: &str, text: &str, message: &Value) -> Result<Value> {
+    let text = serde_json::to_string(text)?;
+    let text = &text[1..text.len() - 1];
+    let message = message.to_string();
+
+    // a single pass, so neither gets placeholders inside the other replaced
+    let mut body = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(i) = rest.find('{') {
+        body.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let (value, len) = if rest.starts_with("{text}") {
+            (text, "{text}".len())
+        } else if rest.starts_with("{message}") {
+            (message.as_str(), "{message}".len())
+        } else {
+            ("{", 1)
+        };
+        body.push_str(value);
+        rest = &rest[len..];
+    }
+    body.push_str(rest);
+    Ok(serde_json::from_str(&body)?)
+}
+
+#[async_trait]
+impl Sender for Json {
+    async fn send<M>(&mut self, msg: M) -> Result<()>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        let message = serde_json::to_value(&msg)?;
+        let body = match &self.template {
+            Some(template) => render(template, msg.to_string().trim(), &message)?,
+            None => message,
+        };
+        log::info!("posting log to {}", self.url);
+        let (client, url) = (&self.client, &self.url);
+        self.retry
+            .run("posting to json webhook", || async {
+                let res = client.post(url).json(&body).send().await?;
+                rejected(res, "webhook").await
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+// logs why a message was turned down, what's wrong with it won't change by
+// sending it again
+async fn rejected(res: reqwest::Response, what: &str) -> Result<reqwest::Response> {
+    let status = res.status();
+    if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+        log::warn!("{} rejected message: {}", what, res.text().await?);
+        return Err(Error::Status(status, None));
+    }
+    retry::check(res)
+}
+
+// a webhook of any kind
+#[derive(Debug)]
+pub enum Hook {
+    Discord(Webhook),
+    Slack(Slack),
+    Matrix(Matrix),
+    Json(Json),
+    Sink(Sink),
+}
+
+#[async_trait]
+impl Sender for Hook {
+    async fn send<M>(&mut self, msg: M) -> Result<()>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        self.send_with_files(msg, &[]).await
+    }
+
+    async fn send_with_files<M>(&mut self, msg: M, files: &[PathBuf]) -> Result<()>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        match self {
+            Self::Discord(hook) => hook.send_with_files(msg, files).await,
+            Self::Slack(hook) => hook.send_with_files(msg, files).await,
+            Self::Matrix(hook) => hook.send_with_files(msg, files).await,
+            Self::Json(hook) => hook.send_with_files(msg, files).await,
+            Self::Sink(sink) => sink.send_with_files(msg, files).await,
+        }
+    }
+
+    async fn send_logs<G>(
+        &mut self,
+        msg_gen: &G,
+        logs: &[LogInfo<'_>],
+        files: &[PathBuf],
+    ) -> Result<()>
+    where
+        G: Generator + Sync,
+        G::Message: Send + Sync,
+    {
+        match self {
+            Self::Discord(hook) => hook.send_logs(msg_gen, logs, files).await,
+            Self::Slack(hook) => hook.send_logs(msg_gen, logs, files).await,
+            Self::Matrix(hook) => hook.send_logs(msg_gen, logs, files).await,
+            Self::Json(hook) => hook.send_logs(msg_gen, logs, files).await,
+            Self::Sink(sink) => sink.send_logs(msg_gen, logs, files).await,
+        }
+    }
+
+    async fn send_or_edit<M>(&mut self, msg: M, id: Option<&str>) -> Result<Option<String>>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        match self {
+            Self::Discord(hook) => hook.send_or_edit(msg, id).await,
+            Self::Slack(hook) => hook.send_or_edit(msg, id).await,
+            Self::Matrix(hook) => hook.send_or_edit(msg, id).await,
+            Self::Json(hook) => hook.send_or_edit(msg, id).await,
+            Self::Sink(sink) => sink.send_or_edit(msg, id).await,
+        }
+    }
+
+    fn can_edit(&self) -> bool {
+        match self {
+            Self::Discord(hook) => hook.can_edit(),
+            Self::Slack(hook) => hook.can_edit(),
+            Self::Matrix(hook) => hook.can_edit(),
+            Self::Json(hook) => hook.can_edit(),
+            Self::Sink(sink) => sink.can_edit(),
+        }
+    }
+}
+
+// somewhere a fan-out sends to
+pub struct Destination<S: Sender> {
+    pub name: String,
+    pub sender: S,
+    pub only: Only,
+    pub style: Style,
+    pub mentions: Mentions,
+    pub templates: Templates,
+}
+
+impl<S: Sender> Destination<S> {
+    fn wanted<'a>(&self, logs: &[LogInfo<'a>]) -> Vec<LogInfo<'a>> {
+        logs.iter()
+            .filter(|log| self.only.wants(log))
+            .cloned()
+            .collect()
+    }
+}
+
+// sends to several destinations, each picking which logs it wants & how
+// messages about them look, one failing doesn't stop the others
+//
+// only fails if every destination does, so retrying later doesn't send the
+// same message twice to the ones that got it
+pub struct Fanout<S: Sender> {
+    destinations: Vec<Destination<S>>,
+}
+
+impl<S: Sender> Fanout<S> {
+    pub fn new(destinations: Vec<Destination<S>>) -> Self {
+        Self { destinations }
+    }
+
+    fn outcome(results: Vec<(&str, Result<()>)>) -> Result<()> {
+        let mut first = None;
+        let mut sent = false;
+        for (name, res) in results {
+            match res {
+                Ok(()) => sent = true,
+                Err(e) => {
+                    log::warn!("failed to send to `{}`: {}", name, e);
+                    first.get_or_insert(e);
+                }
+            }
+        }
+        match first {
+            Some(e) if !sent => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Sender + Sync> Sender for Fanout<S> {
+    async fn send<M>(&mut self, msg: M) -> Result<()>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        self.send_with_files(msg, &[]).await
+    }
+
+    async fn send_with_files<M>(&mut self, msg: M, files: &[PathBuf]) -> Result<()>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        let mut results = Vec::new();
+        for destination in &mut self.destinations {
+            let res = destination.sender.send_with_files(&msg, files).await;
+            results.push((destination.name.as_str(), res));
+        }
+        Self::outcome(results)
+    }
+
+    async fn send_logs<G>(&mut self, _: &G, logs: &[LogInfo<'_>], files: &[PathBuf]) -> Result<()>
+    where
+        G: Generator + Sync,
+        G::Message: Send + Sync,
+    {
+        let mut results = Vec::new();
+        for destination in &mut self.destinations {
+            let logs = destination.wanted(logs);
+            if logs.is_empty() {
+                log::debug!("nothing for `{}`", destination.name);
+                continue;
+            }
+
+            let sender = &mut destination.sender;
+            let res = match destination.style {
+                Style::Embed => {
+                    let msg_gen = WebhookGenerator {
+                        mentions: destination.mentions.clone(),
+                        templates: destination.templates.clone(),
+                    };
+                    sender.send_logs(&msg_gen, &logs, files).await
+                }
+                Style::Text => {
+                    let msg_gen = TextGenerator {
+                        templates: destination.templates.clone(),
+                    };
+                    sender.send_logs(&msg_gen, &logs, files).await
+                }
+            };
+            results.push((destination.name.as_str(), res));
+        }
+        Self::outcome(results)
+    }
+
+    // each destination's message is kept track of separately, the id is a
+    // JSON object of destination names to their message ids
+    async fn send_or_edit<M>(&mut self, msg: M, id: Option<&str>) -> Result<Option<String>>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        let mut ids: BTreeMap<String, String> = id
+            .and_then(|id| serde_json::from_str(id).ok())
+            .unwrap_or_default();
+        let mut results = Vec::new();
+        for destination in &mut self.destinations {
+            let id = ids.get(&destination.name).cloned();
+            let res = match destination.sender.send_or_edit(&msg, id.as_deref()).await {
+                Ok(Some(id)) => {
+                    ids.insert(destination.name.clone(), id);
+                    Ok(())
+                }
+                Ok(None) => Ok(()),
+                Err(e) => Err(e),
+            };
+            results.push((destination.name.as_str(), res));
+        }
+        Self::outcome(results)?;
+
+        if ids.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::to_string(&ids)?))
+    }
+
+    fn can_edit(&self) -> bool {
+        self.destinations
+            .iter()
+            .any(|destination| destination.sender.can_edit())
+    }
+
+    // like `send_logs`, each destination gets the logs it wants looking how
+    // it wants, & like `send_or_edit` keeps track of its own message
+    async fn send_live<G>(
+        &mut self,
+        _: &G,
+        session: &Session,
+        logs: &[LogInfo<'_>],
+        new: &[LogInfo<'_>],
+        files: &[PathBuf],
+        id: Option<&str>,
+    ) -> Result<Option<String>>
+    where
+        G: SessionGenerator + Sync,
+        G::Message: Send + Sync,
+    {
+        let mut ids: BTreeMap<String, String> = id
+            .and_then(|id| serde_json::from_str(id).ok())
+            .unwrap_or_default();
+        let mut results = Vec::new();
+        for destination in &mut self.destinations {
+            let (logs, new) = (destination.wanted(logs), destination.wanted(new));
+            if new.is_empty() {
+                log::debug!("nothing for `{}`", destination.name);
+                continue;
+            }
+
+            let id = ids.get(&destination.name).cloned();
+            let sender = &mut destination.sender;
+            let res = match destination.style {
+                Style::Embed => {
+                    let msg_gen = WebhookGenerator {
+                        mentions: destination.mentions.clone(),
+                        templates: destination.templates.clone(),
+                    };
+                    let id = id.as_deref();
+                    sender
+                        .send_live(&msg_gen, session, &logs, &new, files, id)
+                        .await
+                }
+                Style::Text => {
+                    let msg_gen = TextGenerator {
+                        templates: destination.templates.clone(),
+                    };
+                    let id = id.as_deref();
+                    sender
+                        .send_live(&msg_gen, session, &logs, &new, files, id)
+                        .await
+                }
+            };
+            let res = res.map(|id| {
+                if let Some(id) = id {
+                    ids.insert(destination.name.clone(), id);
+                }
+            });
+            results.push((destination.name.as_str(), res));
+        }
+        Self::outcome(results)?;
+
+        if ids.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::to_string(&ids)?))
+    }
 }
 
 pub struct Write<W: io::Write + Sync + Send> {
@@ -65,4 +1245,106 @@ impl<W: io::Write + Send + Sync> Sender for Write<W> {
         writeln!(self.writer, "{}", msg)?;
         Ok(())
     }
+
+    async fn send_with_files<M>(&mut self, msg: M, files: &[PathBuf]) -> Result<()>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        writeln!(self.writer, "{}", msg)?;
+        for path in files {
+            writeln!(self.writer, "attached {}", path.display())?;
+        }
+        Ok(())
+    }
+
+    // every version of a live message is written out, which is what a dry
+    // run wants to see
+    fn can_edit(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_oversized_messages() {
+        let embed =
+            |description: &str| serde_json::json!({ "title": "t", "description": description });
+        let embeds: Vec<Value> = (0..12).map(|_| embed("x")).collect();
+        let message = serde_json::json!({ "content": "hi", "username": "snek", "embeds": embeds });
+        let parts = split(message);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0]["embeds"].as_array().unwrap().len(), 10);
+        assert_eq!(parts[1]["embeds"].as_array().unwrap().len(), 2);
+        assert_eq!(parts[0]["content"], "hi");
+        assert_eq!(parts[1]["content"], "");
+        assert_eq!(parts[1]["username"], "snek");
+
+        let long = "x".repeat(4000);
+        let message = serde_json::json!({ "embeds": [embed(&long), embed(&long)] });
+        let parts = split(message);
+        assert_eq!(parts.len(), 2);
+        let huge = serde_json::json!({ "embeds": [embed(&"x".repeat(10000))] });
+        let description = split(huge)[0]["embeds"][0]["description"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+        assert_eq!(description.chars().count(), MAX_DESCRIPTION_CHARS);
+
+        let content = format!("{}\n{}", "a".repeat(1500), "b".repeat(1500));
+        let parts = split(serde_json::json!({ "content": content }));
+        assert_eq!(parts.len(), 2);
+        assert!(parts[1]["content"].as_str().unwrap().starts_with('b'));
+    }
+
+    #[test]
+    fn validates_urls() {
+        for url in &[
+            "https://discordapp.com/api/webhooks/ABCDEFGHIJKLMNOPQR/ABCDEFGHIJKLMNOPQRSTUVWXYZ01234567890ABCDEFGHIJKLMNOPQRSTUVWXYZ01234",
+            "https://discord.com/api/webhooks/123456789012345678/abc-DEF_123",
+            "https://canary.discord.com/api/v10/webhooks/123456789012345678/abc",
+        ] {
+            assert!(Kind::Discord.validate_url(url), "{}", url);
+        }
+        for url in &[
+            "http://discord.com/api/webhooks/1/abc",
+            "https://discord.com/api/webhooks/1",
+            "https://evil.com/api/webhooks/1/abc",
+            "https://hooks.slack.com/services/T0000/B0000/XXXX",
+        ] {
+            assert!(!Kind::Discord.validate_url(url), "{}", url);
+        }
+
+        assert!(Kind::Slack.validate_url("https://hooks.slack.com/services/T0000/B0000/XXXX"));
+        assert!(!Kind::Slack.validate_url("https://hooks.slack.com/services/T0000"));
+        assert!(Kind::Matrix.validate_url("https://matrix.org"));
+        assert!(Kind::Json.validate_url("http://localhost:8080/hook"));
+        assert!(!Kind::Json.validate_url("ftp://example.com"));
+    }
+
+    #[test]
+    fn renders_templates() {
+        let message = serde_json::json!({ "content": "hi" });
+        let body = render(
+            r#"{"body": "{text}", "raw": {message}}"#,
+            "a \"b\"\n",
+            &message,
+        )
+        .unwrap();
+        assert_eq!(body["body"], "a \"b\"\n");
+        assert_eq!(body["raw"]["content"], "hi");
+
+        let message = serde_json::json!({ "content": "{text}" });
+        let body = render(
+            r#"{"body": "{text}", "raw": {message}}"#,
+            "{message}",
+            &message,
+        )
+        .unwrap();
+        assert_eq!(body["body"], "{message}");
+        assert_eq!(body["raw"]["content"], "{text}");
+        assert!(!Json::validate_template(r#"{"body": {text}}"#));
+    }
 }
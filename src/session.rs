@@ -0,0 +1,304 @@
+use crate::{
+    history::Record,
+    log::Log,
+    parse::{Encounter, Time},
+    target::{Fractal, Target},
+};
+
+use chrono::{NaiveDate, TimeZone, Utc};
+
+pub(crate) const DAY: i64 = 24 * 60 * 60 * 1000;
+
+// daily reset happens at 00:00 UTC, so the date of a reset is the UTC date
+pub fn reset_date(time: i64) -> Option<NaiveDate> {
+    Utc.timestamp_millis_opt(time)
+        .single()
+        .map(|time| time.date_naive())
+}
+
+// unix timestamp in milliseconds of the reset starting on `date`
+pub fn reset_start(date: NaiveDate) -> i64 {
+    Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+        .timestamp_millis()
+}
+
+// a single log, all times are unix timestamps in milliseconds
+#[derive(Debug, Clone)]
+pub struct Pull {
+    pub log: Log,
+    pub encounters: Vec<Encounter>,
+    // remembered once the log has been uploaded
+    pub record: Record,
+    pub start: i64,
+}
+
+impl Pull {
+    pub fn new(log: Log, encounters: Vec<Encounter>, record: Record, start: i64) -> Self {
+        Self {
+            log,
+            encounters,
+            record,
+            start,
+        }
+    }
+
+    pub const fn target(&self) -> Target {
+        self.log.target()
+    }
+
+    pub fn success(&self) -> bool {
+        !self.encounters.is_empty() && self.encounters.iter().all(|e| e.success)
+    }
+
+    pub fn end(&self) -> i64 {
+        let duration: Time = self.encounters.iter().map(|e| e.phases[0].duration()).sum();
+        self.start + duration as i64
+    }
+
+    fn reset(&self) -> i64 {
+        self.start.div_euclid(DAY)
+    }
+}
+
+// contiguous pulls within the same daily reset
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub pulls: Vec<Pull>,
+}
+
+// pulls further apart than `gap` milliseconds end up in separate sessions
+pub fn group(mut pulls: Vec<Pull>, gap: i64) -> Vec<Session> {
+    pulls.sort_by_key(|pull| pull.start);
+
+    let mut sessions: Vec<Session> = Vec::new();
+    for pull in pulls {
+        let same_session = sessions
+            .last()
+            .and_then(|session| session.pulls.last())
+            .is_some_and(|last| last.reset() == pull.reset() && pull.start - last.end() <= gap);
+
+        match sessions.last_mut() {
+            Some(session) if same_session => session.pulls.push(pull),
+            _ => sessions.push(Session { pulls: vec![pull] }),
+        }
+    }
+
+    sessions
+}
+
+impl Session {
+    pub fn start(&self) -> i64 {
+        self.pulls.first().map_or(0, |pull| pull.start)
+    }
+
+    pub fn end(&self) -> i64 {
+        self.pulls.iter().map(Pull::end).max().unwrap_or(0)
+    }
+
+    // the date of the daily reset the session was played in
+    pub fn date(&self) -> Option<NaiveDate> {
+        reset_date(self.start())
+    }
+
+    // the last kill of each target
+    pub fn kills(&self) -> Vec<&Pull> {
+        let mut kills: Vec<&Pull> = Vec::new();
+        for pull in self.pulls.iter().filter(|pull| pull.success()) {
+            match kills.iter_mut().find(|kill| kill.target() == pull.target()) {
+                Some(kill) => *kill = pull,
+                None => kills.push(pull),
+            }
+        }
+        kills
+    }
+
+    // one run per fractal that was attempted, in the order they were started
+    pub fn runs(&self) -> Vec<Run> {
+        let mut runs: Vec<Run> = Vec::new();
+        for pull in &self.pulls {
+            let fractal = match pull.target().fractal() {
+                Some(fractal) => fractal,
+                None => continue,
+            };
+
+            let run = match runs.iter_mut().find(|run| run.fractal == fractal) {
+                Some(run) => run,
+                None => {
+                    runs.push(Run::new(fractal, pull.start));
+                    runs.last_mut().unwrap()
+                }
+            };
+            run.add(pull);
+        }
+        runs
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub fractal: Fractal,
+    pub pulls: usize,
+    pub wipes: usize,
+    // first pull to last kill
+    pub start: i64,
+    pub end: i64,
+    killed: Vec<Target>,
+}
+
+impl Run {
+    fn new(fractal: Fractal, start: i64) -> Self {
+        Self {
+            fractal,
+            pulls: 0,
+            wipes: 0,
+            start,
+            end: start,
+            killed: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, pull: &Pull) {
+        self.pulls += 1;
+        if pull.success() {
+            self.end = self.end.max(pull.end());
+            if !self.killed.contains(&pull.target()) {
+                self.killed.push(pull.target());
+            }
+        } else {
+            self.wipes += 1;
+        }
+    }
+
+    pub fn cleared(&self) -> bool {
+        self.fractal
+            .targets()
+            .iter()
+            .all(|target| self.killed.contains(target))
+    }
+
+    // only known if every boss of the fractal was killed
+    pub fn clear_time(&self) -> Option<Time> {
+        if self.cleared() {
+            Some((self.end - self.start) as Time)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60 * 1000;
+    // 2020-09-13 12:26:40 UTC
+    const NOON: i64 = 1_600_000_000_000;
+
+    fn pull(target: Target, success: bool, start: i64, minutes: Time) -> Pull {
+        let log = Log::new(target, format!("{:?}/{}.zevtc", target, start));
+        let encounters = vec![Encounter {
+            target,
+            success,
+            phases: vec![(0, minutes * 60 * 1000).into()],
+        }];
+        let record = serde_json::from_value(serde_json::json!({
+            "target": target,
+            "file": log.file_name(),
+            "encounters": [],
+        }))
+        .unwrap();
+        Pull::new(log, encounters, record, start)
+    }
+
+    #[test]
+    fn finds_reset_dates() {
+        let date = NaiveDate::from_ymd_opt(2020, 9, 13).unwrap();
+        assert_eq!(Some(date), reset_date(NOON));
+        assert_eq!(1_599_955_200_000, reset_start(date));
+        assert_eq!(Some(date), reset_date(reset_start(date)));
+        assert_eq!(date.pred_opt(), reset_date(reset_start(date) - 1));
+        assert_eq!(NaiveDate::from_ymd_opt(1969, 12, 31), reset_date(-1));
+    }
+
+    #[test]
+    fn groups_pulls_into_sessions() {
+        let next_reset = reset_start(reset_date(NOON).unwrap().succ_opt().unwrap());
+        let pulls = vec![
+            // the order they're found in doesn't matter
+            pull(Target::Siax, true, NOON + 10 * MINUTE, 2),
+            pull(Target::Mama, true, NOON, 3),
+            // a long break
+            pull(Target::Enso, true, NOON + 60 * MINUTE, 2),
+            // right before and after the next reset
+            pull(Target::Skor, false, next_reset - 5 * MINUTE, 1),
+            pull(Target::Skor, true, next_reset + MINUTE, 2),
+        ];
+
+        let sessions = group(pulls, 30 * MINUTE);
+        let targets: Vec<Vec<Target>> = sessions
+            .iter()
+            .map(|session| session.pulls.iter().map(Pull::target).collect())
+            .collect();
+        assert_eq!(
+            vec![
+                vec![Target::Mama, Target::Siax],
+                vec![Target::Enso],
+                vec![Target::Skor],
+                vec![Target::Skor],
+            ],
+            targets
+        );
+        assert_eq!(NOON, sessions[0].start());
+        assert_eq!(NOON + 12 * MINUTE, sessions[0].end());
+        assert_eq!(reset_date(NOON), sessions[0].date());
+        assert_ne!(sessions[2].date(), sessions[3].date());
+        assert!(group(Vec::new(), MINUTE).is_empty());
+    }
+
+    #[test]
+    fn counts_runs_and_clear_times() {
+        let session = Session {
+            pulls: vec![
+                pull(Target::Skor, false, NOON, 1),
+                pull(Target::Skor, true, NOON + 2 * MINUTE, 2),
+                pull(Target::Mama, true, NOON + 5 * MINUTE, 2),
+                pull(Target::Arts, false, NOON + 10 * MINUTE, 1),
+                pull(Target::Arts, true, NOON + 12 * MINUTE, 2),
+                pull(Target::Arkk, false, NOON + 16 * MINUTE, 3),
+                pull(Target::Arkk, true, NOON + 20 * MINUTE, 4),
+                pull(Target::Golem, true, NOON + 30 * MINUTE, 1),
+            ],
+        };
+
+        let runs = session.runs();
+        let fractals: Vec<Fractal> = runs.iter().map(|run| run.fractal).collect();
+        assert_eq!(
+            vec![Fractal::ShatteredObservatory, Fractal::Nightmare],
+            fractals
+        );
+
+        // skorvald, artsariiv and arkk
+        let shattered = &runs[0];
+        assert_eq!((6, 3), (shattered.pulls, shattered.wipes));
+        assert!(shattered.cleared());
+        assert_eq!(Some(24 * MINUTE as Time), shattered.clear_time());
+
+        // siax and ensolyss were never pulled
+        let nightmare = &runs[1];
+        assert_eq!((1, 0), (nightmare.pulls, nightmare.wipes));
+        assert!(!nightmare.cleared());
+        assert_eq!(None, nightmare.clear_time());
+
+        let kills: Vec<Target> = session.kills().into_iter().map(Pull::target).collect();
+        assert_eq!(
+            vec![
+                Target::Skor,
+                Target::Mama,
+                Target::Arts,
+                Target::Arkk,
+                Target::Golem
+            ],
+            kills
+        );
+    }
+}
@@ -0,0 +1,330 @@
+use crate::{
+    core::LogInfo,
+    error::Result,
+    message::{fmt_time2, fmt_time3, Generator},
+    sender::Sender,
+};
+
+use std::{
+    fmt::Display,
+    fs::{self, OpenOptions},
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use chrono::{Local, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use serde::Serialize;
+
+// how records are written down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // a JSON object per line
+    Jsonl,
+    Csv,
+    // a table, on a page of its own
+    Markdown,
+    Html,
+}
+
+// one encounter, durations are in seconds
+#[derive(Debug, Clone, Serialize)]
+pub struct Row {
+    // in UTC if the log says when it started, otherwise local time according
+    // to the file name
+    pub date: String,
+    pub target: String,
+    pub success: bool,
+    pub duration: f64,
+    pub phases: Vec<PhaseRow>,
+    pub players: Vec<String>,
+    pub link: Option<String>,
+    pub file: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseRow {
+    pub name: Option<String>,
+    pub duration: f64,
+}
+
+impl Row {
+    pub fn new(info: &LogInfo) -> Self {
+        let phases = info
+            .encounter
+            .phases
+            .iter()
+            .skip(1)
+            .map(|phase| PhaseRow {
+                name: phase.name.clone(),
+                duration: secs(phase.duration()),
+            })
+            .collect();
+
+        Self {
+            date: date(info.log.start, info.log.log.path()),
+            target: info.encounter.target.to_string(),
+            success: info.encounter.success,
+            duration: secs(info.encounter.phases[0].duration()),
+            phases,
+            players: info.log.players.clone(),
+            link: info.log.link.clone(),
+            file: info.log.log.file_name(),
+        }
+    }
+
+    fn csv(&self) -> String {
+        let phases: Vec<String> = self
+            .phases
+            .iter()
+            .map(|phase| format!("{:.3}", phase.duration))
+            .collect();
+        [
+            self.date.clone(),
+            self.target.clone(),
+            self.success.to_string(),
+            format!("{:.3}", self.duration),
+            phases.join(";"),
+            self.players.join(";"),
+            self.link.clone().unwrap_or_default(),
+            self.file.clone(),
+        ]
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+
+    // what goes in each column of the markdown & html tables, unescaped
+    fn cells(&self) -> [String; 6] {
+        let result = if self.success { "Kill" } else { "Wipe" };
+        let phases: Vec<String> = self
+            .phases
+            .iter()
+            .map(|phase| fmt_time2(millis(phase.duration)))
+            .collect();
+        [
+            self.date.replace('T', " ").replace('Z', " UTC"),
+            self.target.clone(),
+            result.to_string(),
+            fmt_time3(millis(self.duration)),
+            phases.join(" - "),
+            self.players.join(", "),
+        ]
+    }
+
+    fn markdown(&self) -> String {
+        let mut row = String::from("|");
+        for cell in self.cells().iter() {
+            row += &format!(" {} |", cell.replace('|', "\\|"));
+        }
+        match &self.link {
+            Some(link) => row += &format!(" [{}]({}) |", self.file, link),
+            None => row += &format!(" {} |", self.file),
+        }
+        row
+    }
+
+    fn html(&self) -> String {
+        let mut row = String::from("<tr>");
+        for cell in self.cells().iter() {
+            row += &format!("<td>{}</td>", html_escape(cell));
+        }
+        match &self.link {
+            Some(link) => {
+                row += &format!(
+                    "<td><a href=\"{}\">{}</a></td>",
+                    html_escape(link),
+                    html_escape(&self.file)
+                )
+            }
+            None => row += &format!("<td>{}</td>", html_escape(&self.file)),
+        }
+        row + "</tr>"
+    }
+}
+
+const CSV_HEADER: &str = "date,target,success,duration,phases,players,link,file";
+const MARKDOWN_HEADER: &str = "\
+# snek
+
+| Date | Target | Result | Time | Phases | Players | Log |
+| --- | --- | --- | --- | --- | --- | --- |
+";
+const HTML_PAGE: &str = "\
+<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>snek</title>
+<style>
+body { font-family: sans-serif; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }
+</style>
+</head>
+<body>
+<table>
+<thead>
+<tr><th>Date</th><th>Target</th><th>Result</th><th>Time</th><th>Phases</th><th>Players</th><th>Log</th></tr>
+</thead>
+<tbody>
+</tbody>
+</table>
+</body>
+</html>
+";
+// html rows go right before this, so the page stays whole in between
+const HTML_ROWS_END: &str = "</tbody>";
+
+// keeps a record of every encounter it's sent in a file, adding to what's
+// already there
+#[derive(Debug)]
+pub struct Sink {
+    path: PathBuf,
+    format: Format,
+}
+
+impl Sink {
+    pub fn new(path: impl Into<PathBuf>, format: Format) -> Self {
+        Self {
+            path: path.into(),
+            format,
+        }
+    }
+
+    pub fn write(&self, rows: &[Row]) -> Result<()> {
+        let is_new = fs::metadata(&self.path).map_or(true, |meta| meta.len() == 0);
+        if self.format == Format::Html {
+            return self.write_html(rows, is_new);
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        match self.format {
+            Format::Csv if is_new => writeln!(file, "{}", CSV_HEADER)?,
+            Format::Markdown if is_new => write!(file, "{}", MARKDOWN_HEADER)?,
+            _ => {}
+        }
+        for row in rows {
+            match self.format {
+                Format::Jsonl => writeln!(file, "{}", serde_json::to_string(row)?)?,
+                Format::Csv => writeln!(file, "{}", row.csv())?,
+                Format::Markdown => writeln!(file, "{}", row.markdown())?,
+                Format::Html => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    fn write_html(&self, rows: &[Row], is_new: bool) -> Result<()> {
+        let mut page = if is_new {
+            HTML_PAGE.to_string()
+        } else {
+            fs::read_to_string(&self.path)?
+        };
+        let at = page.rfind(HTML_ROWS_END).unwrap_or(page.len());
+        let rows: String = rows.iter().map(|row| row.html() + "\n").collect();
+        page.insert_str(at, &rows);
+        fs::write(&self.path, page)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sender for Sink {
+    // there's nothing to keep track of in a plain message
+    async fn send<M>(&mut self, _: M) -> Result<()>
+    where
+        M: Display + Serialize + Send + Sync,
+    {
+        log::warn!(
+            "`{}` only keeps track of logs, nothing written",
+            self.path.display()
+        );
+        Ok(())
+    }
+
+    async fn send_logs<G>(&mut self, _: &G, logs: &[LogInfo<'_>], _: &[PathBuf]) -> Result<()>
+    where
+        G: Generator + Sync,
+        G::Message: Send + Sync,
+    {
+        let rows: Vec<Row> = logs.iter().map(Row::new).collect();
+        log::info!("writing {} logs to {}", rows.len(), self.path.display());
+        self.write(&rows)
+    }
+}
+
+fn secs(millis: u64) -> f64 {
+    millis as f64 / 1000.0
+}
+
+fn millis(secs: f64) -> u64 {
+    (secs * 1000.0).round() as u64
+}
+
+// in UTC either way, arcdps naming files after the local time
+fn date(start: Option<i64>, path: &Path) -> String {
+    start
+        .and_then(|start| Utc.timestamp_opt(start, 0).single())
+        .or_else(|| {
+            let stem = path.file_stem()?.to_str()?;
+            let date = NaiveDateTime::parse_from_str(stem.get(..15)?, "%Y%m%d-%H%M%S").ok()?;
+            Some(
+                Local
+                    .from_local_datetime(&date)
+                    .earliest()?
+                    .with_timezone(&Utc),
+            )
+        })
+        .map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+// quoted if it'd be mistaken for more than one field otherwise
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub(crate) fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_csv_fields() {
+        assert_eq!(csv_field("Skorvald"), "Skorvald");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn dates_rows_in_utc() {
+        assert_eq!(
+            date(Some(1789009000), Path::new("20260910-030640.zevtc")),
+            "2026-09-10T02:56:40Z"
+        );
+
+        let local = Local.with_ymd_and_hms(2026, 9, 10, 3, 6, 40).unwrap();
+        assert_eq!(
+            date(None, Path::new("20260910-030640.zevtc")),
+            local
+                .with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::Secs, true)
+        );
+        assert_eq!(date(None, Path::new("x.zevtc")), "");
+    }
+}
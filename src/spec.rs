@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+
+// a profession, or one of its elite specializations
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+pub enum Spec {
+    Guardian,
+    Dragonhunter,
+    Firebrand,
+    Willbender,
+    Warrior,
+    Berserker,
+    Spellbreaker,
+    Bladesworn,
+    Engineer,
+    Scrapper,
+    Holosmith,
+    Mechanist,
+    Ranger,
+    Druid,
+    Soulbeast,
+    Untamed,
+    Thief,
+    Daredevil,
+    Deadeye,
+    Specter,
+    Elementalist,
+    Tempest,
+    Weaver,
+    Catalyst,
+    Mesmer,
+    Chronomancer,
+    Mirage,
+    Virtuoso,
+    Necromancer,
+    Reaper,
+    Scourge,
+    Harbinger,
+    Revenant,
+    Herald,
+    Renegade,
+    Vindicator,
+}
+
+const ALL: &[Spec] = &{
+    use Spec::*;
+    [
+        Guardian,
+        Dragonhunter,
+        Firebrand,
+        Willbender,
+        Warrior,
+        Berserker,
+        Spellbreaker,
+        Bladesworn,
+        Engineer,
+        Scrapper,
+        Holosmith,
+        Mechanist,
+        Ranger,
+        Druid,
+        Soulbeast,
+        Untamed,
+        Thief,
+        Daredevil,
+        Deadeye,
+        Specter,
+        Elementalist,
+        Tempest,
+        Weaver,
+        Catalyst,
+        Mesmer,
+        Chronomancer,
+        Mirage,
+        Virtuoso,
+        Necromancer,
+        Reaper,
+        Scourge,
+        Harbinger,
+        Revenant,
+        Herald,
+        Renegade,
+        Vindicator,
+    ]
+};
+
+impl Spec {
+    // from the profession & elite specialization of an agent in a log, the
+    // latter being 0 for none and 1 for the HoT one in logs from before PoF.
+    // specializations snek doesn't know about yet count as their profession
+    pub fn from_ids(prof: u32, elite: u32) -> Option<Self> {
+        use Spec::*;
+        let profession = match prof {
+            1 => Guardian,
+            2 => Warrior,
+            3 => Engineer,
+            4 => Ranger,
+            5 => Thief,
+            6 => Elementalist,
+            7 => Mesmer,
+            8 => Necromancer,
+            9 => Revenant,
+            _ => return None,
+        };
+        let spec = match elite {
+            1 => ALL[ALL.iter().position(|&spec| spec == profession)? + 1],
+            5 => Druid,
+            7 => Daredevil,
+            18 => Berserker,
+            27 => Dragonhunter,
+            34 => Reaper,
+            40 => Chronomancer,
+            43 => Scrapper,
+            48 => Tempest,
+            52 => Herald,
+            55 => Soulbeast,
+            56 => Weaver,
+            57 => Holosmith,
+            58 => Deadeye,
+            59 => Mirage,
+            60 => Scourge,
+            61 => Spellbreaker,
+            62 => Firebrand,
+            63 => Renegade,
+            64 => Harbinger,
+            65 => Willbender,
+            66 => Virtuoso,
+            67 => Catalyst,
+            68 => Bladesworn,
+            69 => Vindicator,
+            70 => Mechanist,
+            71 => Specter,
+            72 => Untamed,
+            _ => profession,
+        };
+        // a spec of another profession means the ids are off somewhere
+        if spec.profession() != profession {
+            return Some(profession);
+        }
+        Some(spec)
+    }
+
+    // every profession is listed right before its specializations
+    pub fn profession(self) -> Self {
+        ALL[..=ALL.iter().position(|&spec| spec == self).unwrap()]
+            .iter()
+            .rev()
+            .find(|spec| spec.is_profession())
+            .copied()
+            .unwrap()
+    }
+
+    pub fn is_profession(self) -> bool {
+        use Spec::*;
+        matches!(
+            self,
+            Guardian
+                | Warrior
+                | Engineer
+                | Ranger
+                | Thief
+                | Elementalist
+                | Mesmer
+                | Necromancer
+                | Revenant
+        )
+    }
+}
+
+impl std::fmt::Display for Spec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::str::FromStr for Spec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ALL.iter()
+            .find(|spec| spec.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("unknown profession or specialization `{}`", s))
+    }
+}
+
+impl std::convert::TryFrom<String> for Spec {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Spec> for String {
+    fn from(spec: Spec) -> Self {
+        spec.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_ids() {
+        assert_eq!(Spec::from_ids(8, 34), Some(Spec::Reaper));
+        assert_eq!(Spec::from_ids(8, 0), Some(Spec::Necromancer));
+        assert_eq!(Spec::from_ids(8, 99), Some(Spec::Necromancer));
+        // firebrand isn't a necromancer spec
+        assert_eq!(Spec::from_ids(8, 62), Some(Spec::Necromancer));
+        assert_eq!(Spec::from_ids(10, 0), None);
+    }
+
+    #[test]
+    fn maps_hot_specs() {
+        use Spec::*;
+        let hot = [
+            Dragonhunter,
+            Berserker,
+            Scrapper,
+            Druid,
+            Daredevil,
+            Tempest,
+            Chronomancer,
+            Reaper,
+            Herald,
+        ];
+        for (prof, spec) in (1..).zip(hot) {
+            assert_eq!(Spec::from_ids(prof, 1), Some(spec));
+        }
+    }
+
+    #[test]
+    fn parses_specs() {
+        assert_eq!("firebrand".parse(), Ok(Spec::Firebrand));
+        assert!("soldier".parse::<Spec>().is_err());
+    }
+}
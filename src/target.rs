@@ -48,6 +48,34 @@ impl Target {
         }
     }
 
+    pub const fn fractal(self) -> Option<Fractal> {
+        use Target::*;
+        match self {
+            Mama | Siax | Enso => Some(Fractal::Nightmare),
+            Skor | Arts | Arkk => Some(Fractal::ShatteredObservatory),
+            Ai | AiElemental | AiDark => Some(Fractal::SunquaPeak),
+            Golem => None,
+        }
+    }
+
+    // picture of the boss from the wiki, shown next to messages about it
+    pub const fn thumbnail(self) -> Option<&'static str> {
+        use Target::*;
+        let url = match self {
+            Mama => "https://wiki.guildwars2.com/wiki/Special:FilePath/MAMA.jpg",
+            Siax => "https://wiki.guildwars2.com/wiki/Special:FilePath/Siax_the_Corrupted.jpg",
+            Enso => "https://wiki.guildwars2.com/wiki/Special:FilePath/Ensolyss_of_the_Endless_Torment.jpg",
+            Skor => "https://wiki.guildwars2.com/wiki/Special:FilePath/Skorvald_the_Shattered.jpg",
+            Arts => "https://wiki.guildwars2.com/wiki/Special:FilePath/Artsariiv.jpg",
+            Arkk => "https://wiki.guildwars2.com/wiki/Special:FilePath/Arkk.jpg",
+            Ai | AiElemental | AiDark => {
+                "https://wiki.guildwars2.com/wiki/Special:FilePath/Ai,_Keeper_of_the_Peak.jpg"
+            }
+            Golem => return None,
+        };
+        Some(url)
+    }
+
     pub const fn from_id(id: u16) -> Option<Self> {
         use Target::*;
         let target = match id {
@@ -65,6 +93,38 @@ impl Target {
     }
 }
 
+// the fractal each boss is fought in, used to group logs into full runs
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Fractal {
+    Nightmare,
+    ShatteredObservatory,
+    SunquaPeak,
+}
+
+impl Fractal {
+    // in the order they're fought
+    pub const fn targets(self) -> &'static [Target] {
+        use Target::*;
+        match self {
+            Self::Nightmare => &[Mama, Siax, Enso],
+            Self::ShatteredObservatory => &[Skor, Arts, Arkk],
+            Self::SunquaPeak => &[Ai],
+        }
+    }
+}
+
+impl std::fmt::Display for Fractal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Nightmare => "Nightmare",
+            Self::ShatteredObservatory => "Shattered Observatory",
+            Self::SunquaPeak => "Sunqua Peak",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
 impl std::fmt::Display for Target {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let name = match self {
@@ -78,3 +138,25 @@ impl std::fmt::Display for Target {
         write!(f, "{}", name)
     }
 }
+
+// accepts the short names used throughout snek as well as the log directory
+// names, i.e. `skor` or `Skorvald the Shattered`
+impl std::str::FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Target::*;
+        let target = match s.to_lowercase().as_str() {
+            "mama" => Mama,
+            "siax" => Siax,
+            "enso" => Enso,
+            "skor" => Skor,
+            "arts" => Arts,
+            "arkk" => Arkk,
+            "ai" => Ai,
+            "golem" => Golem,
+            _ => return Self::from_name(s).ok_or_else(|| format!("unknown target `{}`", s)),
+        };
+        Ok(target)
+    }
+}
@@ -0,0 +1,304 @@
+use crate::spec::Spec;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+// what a template can refer to about a log
+pub const FIELDS: &[&str] = &[
+    // i.e. Skorvald the Shattered
+    "target",
+    // the link to the log, or its file name if it wasn't uploaded
+    "link",
+    "file",
+    // Success or Defeat
+    "status",
+    // i.e. 1:19.760, and 1:19.76
+    "duration",
+    "duration_short",
+    // each phase's duration
+    "phases",
+    "players",
+    // players with what they played, i.e. One.1234 (Firebrand)
+    "squad",
+    // how the kill compares to the fastest one, empty if it doesn't
+    "pb_delta",
+    // a line per phase compared to the fastest one and the sum of best, each
+    // starting with a newline
+    "splits",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field(String),
+    // only kept if the field isn't empty
+    Section(String, Vec<Part>),
+}
+
+// a layout where `{field}` is replaced by that field and anything between
+// `{?field}` and `{/field}` is left out if the field is empty, `{{` and `}}`
+// being literal braces
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn render(&self, fields: &HashMap<&str, String>) -> String {
+        let mut out = String::new();
+        render(&self.parts, fields, &mut out);
+        out
+    }
+}
+
+fn render(parts: &[Part], fields: &HashMap<&str, String>, out: &mut String) {
+    for part in parts {
+        match part {
+            Part::Text(text) => out.push_str(text),
+            Part::Field(name) => {
+                if let Some(value) = fields.get(name.as_str()) {
+                    out.push_str(value);
+                }
+            }
+            Part::Section(name, parts) => {
+                if fields
+                    .get(name.as_str())
+                    .is_some_and(|value| !value.is_empty())
+                {
+                    render(parts, fields, out);
+                }
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        // sections that are still open, with what came before them
+        let mut stack: Vec<(String, Vec<Part>)> = Vec::new();
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err(format!("unmatched `}}` in template `{}`", s)),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("unclosed `{{` in template `{}`", s)),
+                        }
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+
+                    let (kind, field) = match name.chars().next() {
+                        Some(kind @ ('?' | '/')) => (Some(kind), &name[1..]),
+                        _ => (None, name.as_str()),
+                    };
+                    if !FIELDS.contains(&field) {
+                        return Err(format!(
+                            "unknown field `{}` in template `{}`, expected one of {}",
+                            field,
+                            s,
+                            FIELDS.join(", ")
+                        ));
+                    }
+                    match kind {
+                        Some('?') => stack.push((field.to_string(), std::mem::take(&mut parts))),
+                        Some(_) => match stack.pop() {
+                            Some((open, outer)) if open == field => {
+                                let section = std::mem::replace(&mut parts, outer);
+                                parts.push(Part::Section(open, section));
+                            }
+                            _ => {
+                                return Err(format!(
+                                    "`{{/{}}}` doesn't close anything in template `{}`",
+                                    field, s
+                                ))
+                            }
+                        },
+                        None => parts.push(Part::Field(field.to_string())),
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+        if let Some((open, _)) = stack.pop() {
+            return Err(format!(
+                "`{{?{}}}` is never closed in template `{}`",
+                open, s
+            ));
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Ok(Self {
+            source: s.to_string(),
+            parts,
+        })
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl std::convert::TryFrom<String> for Template {
+    type Error = String;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Template> for String {
+    fn from(template: Template) -> Self {
+        template.source
+    }
+}
+
+// what's listed under an embed's description
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmbedField {
+    // one per phase
+    Phases,
+    Squad,
+    // the 5 players that did the most damage to the boss
+    Dps,
+}
+
+// how messages about logs are laid out, markdown and all
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Templates {
+    // a line per log in text messages
+    pub text: Template,
+    // an embed per log otherwise
+    pub title: Template,
+    pub description: Template,
+    pub success_color: u32,
+    pub failure_color: u32,
+    pub embed_fields: Vec<EmbedField>,
+    pub squad: Squad,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        let template = |s: &str| s.parse().unwrap();
+        Self {
+            text: template("{link} - **{status}** in {duration_short}{?pb_delta} - {pb_delta}{/pb_delta}{splits}{?squad}\n{squad}{/squad}"),
+            title: template("{target}"),
+            description: template(
+                "**{status}** in {duration}{?pb_delta}\n{pb_delta}{/pb_delta}{splits}",
+            ),
+            success_color: 0x15_83_d1,
+            failure_color: 0xd1_3e_15,
+            embed_fields: vec![EmbedField::Phases, EmbedField::Squad, EmbedField::Dps],
+            squad: Squad::default(),
+        }
+    }
+}
+
+// how players are named in messages
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Squad {
+    // leave out whoever recorded the log
+    pub hide_recorder: bool,
+    // `Player 1`, `Player 2` and so on instead of account names
+    pub anonymize: bool,
+    // shown before players by what they played, i.e. `firebrand = "<:fb:123>"`,
+    // a profession's goes for its specializations without one of their own
+    pub emoji: BTreeMap<Spec, String>,
+}
+
+impl Squad {
+    pub fn emoji(&self, spec: Spec) -> Option<&str> {
+        self.emoji
+            .get(&spec)
+            .or_else(|| self.emoji.get(&spec.profession()))
+            .map(String::as_str)
+    }
+
+    // how each of `players` is shown, by account name, leaving out the ones
+    // that shouldn't be
+    pub fn names(&self, players: &[String], recorder: Option<&str>) -> HashMap<String, String> {
+        players
+            .iter()
+            .filter(|player| !(self.hide_recorder && recorder == Some(player.as_str())))
+            .enumerate()
+            .map(|(idx, player)| {
+                let name = if self.anonymize {
+                    format!("Player {}", idx + 1)
+                } else {
+                    player.clone()
+                };
+                (player.clone(), name)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_templates() {
+        let template: Template = "{{{target}}}{?pb_delta} - {pb_delta}{/pb_delta}!"
+            .parse()
+            .unwrap();
+        let mut fields = HashMap::new();
+        fields.insert("target", "Arkk".to_string());
+        assert_eq!(template.render(&fields), "{Arkk}!");
+        fields.insert("pb_delta", "New PB!".to_string());
+        assert_eq!(template.render(&fields), "{Arkk} - New PB!!");
+
+        assert!("{nope}".parse::<Template>().is_err());
+        assert!("{?target}".parse::<Template>().is_err());
+        assert!("{?target}{/players}".parse::<Template>().is_err());
+        assert!("{target".parse::<Template>().is_err());
+        assert!("target}".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn names_squads() {
+        let players = vec!["A.1".to_string(), "B.2".to_string(), "C.3".to_string()];
+        let mut squad = Squad::default();
+        assert_eq!(squad.names(&players, Some("B.2"))["B.2"], "B.2");
+
+        squad.hide_recorder = true;
+        squad.anonymize = true;
+        let names = squad.names(&players, Some("B.2"));
+        assert!(!names.contains_key("B.2"));
+        assert_eq!(names["C.3"], "Player 2");
+
+        squad.emoji.insert(Spec::Guardian, ":shield:".to_string());
+        squad.emoji.insert(Spec::Firebrand, ":fire:".to_string());
+        assert_eq!(squad.emoji(Spec::Firebrand), Some(":fire:"));
+        assert_eq!(squad.emoji(Spec::Willbender), Some(":shield:"));
+        assert_eq!(squad.emoji(Spec::Reaper), None);
+    }
+}
@@ -1,60 +1,349 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use serde::Deserialize;
+use async_trait::async_trait;
+use serde::{Deserialize, Deserializer, Serialize};
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
 
-use crate::error::Result;
+use crate::{
+    error::{Error, Result},
+    retry,
+};
+
+// somewhere logs get turned into a link, `None` if they aren't uploaded at all
+#[async_trait]
+pub trait Uploader: fmt::Debug + Send + Sync {
+    async fn upload(&self, path: &Path) -> Result<Option<Response>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    DpsReport,
+    Wingman,
+    // reports written by a local or self-hosted Elite Insights
+    Local,
+    // nothing gets uploaded, messages go out without links
+    Offline,
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "dps-report" | "dps.report" => Ok(Self::DpsReport),
+            "wingman" => Ok(Self::Wingman),
+            "local" => Ok(Self::Local),
+            "offline" => Ok(Self::Offline),
+            _ => Err(format!(
+                "unknown uploader `{}`, expected dps-report, wingman, local or offline",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DpsReport => write!(f, "dps-report"),
+            Self::Wingman => write!(f, "wingman"),
+            Self::Local => write!(f, "local"),
+            Self::Offline => write!(f, "offline"),
+        }
+    }
+}
+
+// extra parameters dps.report understands, see https://dps.report/api
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UploadOptions {
+    // groups uploads under the same dps.report account
+    pub user_token: Option<String>,
+    pub anonymous: bool,
+    pub detailed_wvw: bool,
+    // i.e. ei, leaving it out uses whatever dps.report defaults to
+    pub generator: Option<String>,
+}
 
 #[derive(Debug)]
-struct Uploader {
+pub struct DpsReport {
     url: String,
+    options: UploadOptions,
     client: reqwest::Client,
 }
 
-pub async fn push(url: &str, path: impl AsRef<Path>) -> Result<Response> {
-    let upload = upload_file(url, path).await?;
-    Ok(upload)
+impl DpsReport {
+    pub fn new(url: &str, options: UploadOptions) -> Self {
+        Self {
+            url: url.to_string(),
+            options,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[async_trait]
+impl Uploader for DpsReport {
+    async fn upload(&self, path: &Path) -> Result<Option<Response>> {
+        let mut form = reqwest::multipart::Form::new().text("json", "1");
+        if let Some(token) = &self.options.user_token {
+            form = form.text("userToken", token.clone());
+        }
+        if self.options.anonymous {
+            form = form.text("anonymous", "true");
+        }
+        if self.options.detailed_wvw {
+            form = form.text("detailedwvw", "true");
+        }
+        if let Some(generator) = &self.options.generator {
+            form = form.text("generator", generator.clone());
+        }
+        let form = form.part("file", file_part(path).await?);
+
+        let url = format!("{}uploadContent", self.url);
+        let res = self.client.post(&url).multipart(form).send().await?;
+        let res: Response = retry::check(res)?.json().await?;
+
+        if self.options.user_token.is_none() {
+            if let Some(token) = &res.user_token {
+                log::trace!("uploaded with user token {}", mask(token));
+            }
+        }
+        Ok(Some(res))
+    }
+}
+
+#[derive(Debug)]
+pub struct Wingman {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl Wingman {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+#[async_trait]
+impl Uploader for Wingman {
+    async fn upload(&self, path: &Path) -> Result<Option<Response>> {
+        let form = reqwest::multipart::Form::new().part("file", file_part(path).await?);
+        let url = format!("{}uploadEVTC", self.url);
+        let res = self.client.post(&url).multipart(form).send().await?;
+        let accepted = retry::check(res)?.text().await?;
+        if accepted.trim() != "True" {
+            log::debug!("wingman answered `{}`", accepted.trim());
+            return Err(Error::RejectedLog(path.to_owned()));
+        }
+
+        // wingman doesn't answer with a link, logs end up under their file name
+        let id = file_stem(path);
+        Ok(Some(Response {
+            permalink: format!("{}log/{}", self.url, id),
+            id,
+            ..Response::default()
+        }))
+    }
+}
+
+// finds the report Elite Insights wrote for a log, in `dir` or next to the log
+// if not set, linking to `base_url` if it's being served somewhere
+#[derive(Debug)]
+pub struct Local {
+    dir: Option<PathBuf>,
+    base_url: Option<String>,
+}
+
+impl Local {
+    pub const fn new(dir: Option<PathBuf>, base_url: Option<String>) -> Self {
+        Self { dir, base_url }
+    }
+}
+
+#[async_trait]
+impl Uploader for Local {
+    async fn upload(&self, path: &Path) -> Result<Option<Response>> {
+        let dir = match &self.dir {
+            Some(dir) => dir.as_path(),
+            None => path.parent().unwrap_or_else(|| Path::new(".")),
+        };
+        let id = file_stem(path);
+
+        // i.e. 20200407-174541_arkk_kill.html
+        let mut entries = tokio::fs::read_dir(dir).await?;
+        let mut report = None;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with(&id) && name.ends_with(".html") {
+                report = Some((entry.path(), name));
+                break;
+            }
+        }
+        let (report, name) = report.ok_or_else(|| Error::MissingReport(path.to_owned()))?;
+
+        let permalink = match &self.base_url {
+            Some(base_url) => format!("{}{}", base_url, name),
+            None => local_link(&report),
+        };
+        Ok(Some(Response {
+            id,
+            permalink,
+            ..Response::default()
+        }))
+    }
+}
+
+#[derive(Debug)]
+pub struct Offline {}
+
+#[async_trait]
+impl Uploader for Offline {
+    async fn upload(&self, _: &Path) -> Result<Option<Response>> {
+        Ok(None)
+    }
+}
+
+pub(crate) fn local_link(path: &Path) -> String {
+    std::fs::canonicalize(path)
+        .ok()
+        .and_then(|path| url::Url::from_file_path(path).ok())
+        .map(String::from)
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// enough of a secret to tell it apart from others, but not to use it
+fn mask(secret: &str) -> String {
+    let shown: String = secret.chars().take(4).collect();
+    format!(
+        "{}{}",
+        shown,
+        "*".repeat(secret.chars().count().saturating_sub(4))
+    )
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Response {
     pub id: String,
     pub permalink: String,
+    pub upload_time: Option<i64>,
+    pub encounter_time: Option<i64>,
+    pub generator: Option<String>,
+    // the token dps.report put the upload under, new if none was given
+    pub user_token: Option<String>,
+    pub encounter: Option<EncounterInfo>,
+    // keyed by account name
+    #[serde(default, deserialize_with = "players")]
+    pub players: HashMap<String, PlayerInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncounterInfo {
+    pub boss: Option<String>,
+    pub boss_id: Option<u32>,
+    pub success: bool,
+    // in seconds
+    pub duration: u64,
+    #[serde(default)]
+    pub is_cm: bool,
+    pub comp_dps: Option<u64>,
+    pub number_of_players: Option<u32>,
+    pub gw2_build: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlayerInfo {
+    pub display_name: String,
+    pub character_name: String,
+    pub profession: u32,
+    pub elite_spec: u32,
 }
 
-async fn read_file(path: impl AsRef<Path>) -> Result<Vec<u8>> {
-    let mut file = File::open(path).await?;
-    let mut file_buf = Vec::new();
-    file.read_to_end(&mut file_buf).await?;
-    Ok(file_buf)
+// an empty player list is sent as `[]` instead of `{}`
+fn players<'de, D>(deserializer: D) -> std::result::Result<HashMap<String, PlayerInfo>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Players {
+        Map(HashMap<String, PlayerInfo>),
+        List(Vec<PlayerInfo>),
+    }
+
+    Ok(match Players::deserialize(deserializer)? {
+        Players::Map(players) => players,
+        Players::List(players) => players
+            .into_iter()
+            .map(|player| (player.display_name.clone(), player))
+            .collect(),
+    })
 }
 
-async fn upload_file(url: &str, path: impl AsRef<Path>) -> Result<Response> {
-    let path = path.as_ref();
+// how much of a log is read into memory at a time while uploading it
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// streams the file from disk rather than reading all of it up front, logging
+// how far along it is every 10%
+pub(crate) async fn file_part(path: &Path) -> Result<reqwest::multipart::Part> {
+    use futures::stream;
+
     let file_name = path
         .file_name()
         .map(|name| name.to_string_lossy().into_owned())
-        .unwrap();
-    let file = read_file(path).await?;
-
-    let part = reqwest::multipart::Part::bytes(file)
-        .file_name(file_name)
-        .mime_str("application/octet-stream")?;
-
-    let form = reqwest::multipart::Form::new()
-        .text("json", "1")
-        .part("file", part);
+        .unwrap_or_default();
+    let file = File::open(path).await?;
+    let size = file.metadata().await?.len();
 
-    let url = format!("{}uploadContent", url);
+    let name = file_name.clone();
+    let chunks = stream::try_unfold((file, 0_u64), move |(mut file, sent)| {
+        let name = name.clone();
+        async move {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let read = file.read(&mut chunk).await?;
+            if read == 0 {
+                return Ok::<_, std::io::Error>(None);
+            }
+            chunk.truncate(read);
 
-    let res = reqwest::Client::new()
-        .post(&url)
-        .multipart(form)
-        .send()
-        .await?
-        .json()
-        .await?;
+            let total = sent + read as u64;
+            if size > 0 && total * 10 / size > sent * 10 / size {
+                log::debug!("uploading {}: {}%", name, total * 100 / size);
+            }
+            Ok(Some((chunk, (file, total))))
+        }
+    });
 
-    Ok(res)
+    // with a length the request doesn't have to be sent in chunks
+    let part =
+        reqwest::multipart::Part::stream_with_length(reqwest::Body::wrap_stream(chunks), size)
+            .file_name(file_name)
+            .mime_str("application/octet-stream")?;
+    Ok(part)
 }
@@ -1,9 +1,13 @@
 use snek::cache;
 use snek::core;
+use snek::message::Format;
+use snek::upload::{self, UploadOptions, Uploader};
 
 use std::collections::HashSet;
 use std::io::Read as _;
+use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::mpsc;
 use std::thread;
 
 use bytes::buf::BufExt as _;
@@ -30,6 +34,32 @@ async fn echo(req: Request<Body>) -> Result<Response<Body>, hyper::Error> {
             Ok(Response::new(Body::from(link)))
         }
 
+        // only takes uploads that say how big they are up front
+        (&Method::POST, "/length/uploadEVTC") => {
+            let length = req
+                .headers()
+                .get(hyper::header::CONTENT_LENGTH)
+                .and_then(|length| length.to_str().ok()?.parse::<usize>().ok());
+            let body = hyper::body::to_bytes(req.into_body()).await?;
+            let accepted = if length == Some(body.len()) {
+                "True"
+            } else {
+                "False"
+            };
+            Ok(Response::new(Body::from(accepted)))
+        }
+
+        (&Method::POST, path) if path.ends_with("/uploadEVTC") => {
+            // answering before the upload's been read breaks the connection
+            let accepted = if path == "/uploadEVTC" {
+                "True"
+            } else {
+                "False"
+            };
+            hyper::body::to_bytes(req.into_body()).await?;
+            Ok(Response::new(Body::from(accepted)))
+        }
+
         (&Method::POST, "/webhook") => {
             /*
             use snek::message::WebhookMessage;
@@ -48,14 +78,18 @@ async fn echo(req: Request<Body>) -> Result<Response<Body>, hyper::Error> {
     }
 }
 
-async fn spawn_env() {
-    let addr = ([127, 0, 0, 1], 8000).into();
+async fn spawn_env(bound: mpsc::Sender<SocketAddr>) {
+    // any free port, so tests running at the same time don't fight over one
+    let addr = ([127, 0, 0, 1], 0).into();
     let service = make_service_fn(|_| async { Ok::<_, hyper::Error>(service_fn(echo)) });
     let server = Server::bind(&addr).serve(service);
+    bound.send(server.local_addr()).unwrap();
     server.await.unwrap();
 }
 
-fn dummy_env() {
+// the url of a stand-in for every server snek talks to
+fn dummy_env() -> String {
+    let (bound, addr) = mpsc::channel();
     thread::spawn(move || {
         let mut rt = Builder::new()
             .basic_scheduler()
@@ -63,8 +97,9 @@ fn dummy_env() {
             .core_threads(1)
             .build()
             .unwrap();
-        rt.block_on(spawn_env())
+        rt.block_on(spawn_env(bound))
     });
+    format!("http://{}/", addr.recv().unwrap())
 }
 
 fn test_log_dir() -> PathBuf {
@@ -77,14 +112,16 @@ fn link_id(link: &str) -> &str {
 
 #[tokio::test]
 async fn links() {
-    dummy_env();
+    let url = dummy_env();
 
     let log_dir = test_log_dir();
     let mut output = Vec::new();
-    let url = "http://127.0.0.1:8000/";
 
     let cache = cache::Nop {};
-    core::links(log_dir, url, &mut output, cache).await.unwrap();
+    let options = core::Options::new(&url).window(core::Window::All);
+    core::links(log_dir, &options, &mut output, cache, Format::Text)
+        .await
+        .unwrap();
 
     let mut buffer = String::new();
     output.as_slice().read_to_string(&mut buffer).unwrap();
@@ -117,25 +154,61 @@ async fn sender() {
     use snek::parse;
     use snek::sender::Sender;
     use snek::sender::Webhook;
-    use snek::upload;
 
-    dummy_env();
+    let upload_url = dummy_env();
 
     let log_path = "tests/example_logs/Arkk/Codpiece/20200408-233716.zevtc";
-    let upload_url = "http://127.0.0.1:8000/";
-    let url = "http://127.0.0.1:8000/webhook";
+    let url = format!("{}webhook", upload_url);
 
-    let message_generator = WebhookGenerator {};
-    let mut webhook_message_sender = Webhook::new(url);
+    let message_generator = WebhookGenerator::default();
+    let mut webhook_message_sender = Webhook::new(&url);
 
-    let log = Log::from_file_checked(&log_path).unwrap();
+    let log = Log::from_file_checked(log_path).unwrap();
     let (mut encounters, _) = parse::parse(&log).unwrap();
     let encounter = encounters.remove(0);
-    let res = upload::push(upload_url, log.path()).await.unwrap();
-    let uploaded_log = UploadedLog::new(log, res.permalink);
+    let uploader = upload::DpsReport::new(&upload_url, UploadOptions::default());
+    let res = uploader.upload(log.path()).await.unwrap().unwrap();
+    let uploaded_log = UploadedLog::new(log, Some(res.permalink));
     let log_info = LogInfo::new(&uploaded_log, encounter);
 
     let message = message_generator.generate(&[log_info]);
 
     webhook_message_sender.send(&message).await.unwrap();
 }
+
+#[tokio::test]
+async fn uploaders() {
+    let url = dummy_env();
+
+    let log_path = PathBuf::from("tests/example_logs/Arkk/Codpiece/20200408-233716.zevtc");
+
+    let wingman = upload::Wingman::new(&url);
+    let res = wingman.upload(&log_path).await.unwrap().unwrap();
+    assert_eq!(res.permalink, format!("{}log/20200408-233716", url));
+
+    // wingman turned it down
+    let wingman = upload::Wingman::new(&format!("{}reject/", url));
+    assert!(wingman.upload(&log_path).await.is_err());
+
+    let offline = upload::Offline {};
+    // nothing to link to
+    assert!(offline.upload(&log_path).await.unwrap().is_none());
+
+    // no report has been written for it
+    let local = upload::Local::new(None, None);
+    assert!(local.upload(&log_path).await.is_err());
+}
+
+#[tokio::test]
+async fn uploads_with_length() {
+    let url = dummy_env();
+
+    // bigger than a single chunk of the upload
+    let log_path = std::env::temp_dir().join("snek-20200408-233716.zevtc");
+    std::fs::write(&log_path, vec![7; 200 * 1024]).unwrap();
+
+    let wingman = upload::Wingman::new(&format!("{}length/", url));
+    let res = wingman.upload(&log_path).await;
+    std::fs::remove_file(&log_path).unwrap();
+    assert!(res.unwrap().is_some());
+}